@@ -0,0 +1,186 @@
+use smart_leds_trait::RGB8;
+
+use crate::pixel::set_channel;
+use crate::Apa102Pixel;
+
+const MAGIC: &[u8; 3] = b"Ada";
+
+/// Decodes the Adalight protocol spoken by ambilight software like Hyperion
+/// and Prismatik
+///
+/// Every frame starts with `Ada`, the number of leds minus one as big endian
+/// `u16` and a checksum, followed by the pixels as RGB triplets. Frames are
+/// decoded into a buffer of `N` pixels, leds beyond that are dropped.
+///
+/// ```no_run
+/// # use apa102_spi::{AdalightDecoder, Apa102, ClockedLedBus};
+/// # use smart_leds_trait::SmartLedsWrite;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # mod nb {
+/// #     macro_rules! block {
+/// #         ($expr:expr) => {
+/// #             $expr
+/// #         };
+/// #     }
+/// #     pub(crate) use block;
+/// # }
+/// # struct Serial;
+/// # impl Serial {
+/// #     fn read(&mut self) -> Result<u8, ()> {
+/// #         Ok(0)
+/// #     }
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let mut serial = Serial;
+/// let mut decoder: AdalightDecoder<100> = AdalightDecoder::new();
+/// loop {
+///     let byte = nb::block!(serial.read())?;
+///     if let Some(frame) = decoder.push(byte) {
+///         apa102.write(frame)?;
+///     }
+/// }
+/// # Ok::<(), ()>(())
+/// ```
+pub struct AdalightDecoder<const N: usize> {
+    state: State,
+    pixels: [RGB8; N],
+}
+
+enum State {
+    /// Number of bytes of the magic matched so far
+    Magic(usize),
+    /// Header bytes following the magic received so far
+    Header([u8; 3], usize),
+    /// Number of pixel bytes of the frame and received so far
+    Data(usize, usize),
+}
+
+impl<const N: usize> AdalightDecoder<N> {
+    pub fn new() -> AdalightDecoder<N> {
+        Self {
+            state: State::Magic(0),
+            pixels: [RGB8::default(); N],
+        }
+    }
+
+    /// Feed the next received byte, returning the pixels once a frame is
+    /// complete
+    ///
+    /// Bytes outside of a frame and frames with a wrong checksum are
+    /// skipped.
+    pub fn push(&mut self, byte: u8) -> Option<impl Iterator<Item = Apa102Pixel> + '_> {
+        match self.state {
+            State::Magic(matched) => {
+                self.state = if byte == MAGIC[matched] {
+                    if matched + 1 == MAGIC.len() {
+                        State::Header([0; 3], 0)
+                    } else {
+                        State::Magic(matched + 1)
+                    }
+                } else {
+                    State::Magic(usize::from(byte == MAGIC[0]))
+                };
+            }
+            State::Header(mut header, received) => {
+                header[received] = byte;
+                self.state = if received + 1 < header.len() {
+                    State::Header(header, received + 1)
+                } else if header[2] == header[0] ^ header[1] ^ 0x55 {
+                    let num_leds = u16::from_be_bytes([header[0], header[1]]) as usize + 1;
+                    State::Data(num_leds * 3, 0)
+                } else {
+                    State::Magic(0)
+                };
+            }
+            State::Data(len, received) => {
+                set_channel(&mut self.pixels, received, byte);
+                if received + 1 < len {
+                    self.state = State::Data(len, received + 1);
+                } else {
+                    self.state = State::Magic(0);
+                    let num_leds = (len / 3).min(N);
+                    return Some(self.pixels[..num_leds].iter().map(|&c| c.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<const N: usize> Default for AdalightDecoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::{colors, feed};
+
+    fn header(num_leds: u16) -> [u8; 6] {
+        let [hi, lo] = (num_leds - 1).to_be_bytes();
+        [b'A', b'd', b'a', hi, lo, hi ^ lo ^ 0x55]
+    }
+
+    #[test]
+    fn decodes_frame() {
+        let mut decoder: AdalightDecoder<4> = AdalightDecoder::new();
+        let mut bytes = header(2).to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(1, 2, 3), RGB8::new(4, 5, 6)]]);
+    }
+
+    #[test]
+    fn drops_leds_beyond_buffer() {
+        let mut decoder: AdalightDecoder<1> = AdalightDecoder::new();
+        let mut bytes = header(2).to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(1, 2, 3)]]);
+    }
+
+    #[test]
+    fn skips_frame_with_bad_checksum() {
+        let mut decoder: AdalightDecoder<4> = AdalightDecoder::new();
+        let mut bad = header(1);
+        bad[5] ^= 0xFF;
+        let mut bytes = bad.to_vec();
+        bytes.extend_from_slice(&[1, 2, 3]);
+        bytes.extend_from_slice(&header(1));
+        bytes.extend_from_slice(&[4, 5, 6]);
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(4, 5, 6)]]);
+    }
+
+    #[test]
+    fn skips_bytes_past_announced_length() {
+        let mut decoder: AdalightDecoder<4> = AdalightDecoder::new();
+        let mut bytes = header(1).to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 7, 8, 9]);
+        bytes.extend_from_slice(&header(1));
+        bytes.extend_from_slice(&[4, 5, 6]);
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(1, 2, 3)], [RGB8::new(4, 5, 6)]]);
+    }
+
+    #[test]
+    fn resyncs_after_garbage() {
+        let mut decoder: AdalightDecoder<4> = AdalightDecoder::new();
+        // Ends in a partial magic, restarted by the magic of the frame
+        let mut bytes = b"xAdA".to_vec();
+        bytes.extend_from_slice(&header(1));
+        bytes.extend_from_slice(&[1, 2, 3]);
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(1, 2, 3)]]);
+    }
+}
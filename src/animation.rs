@@ -0,0 +1,145 @@
+use core::convert::Infallible;
+
+use embedded_hal::delay::DelayNs;
+
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::{beat8, beatsin8, Apa102Pixel, Fire, Gradient, Hsv};
+
+/// An animation rendering one frame at a time
+pub trait Effect {
+    /// Render the frame shown `t_ms` milliseconds after the animation started
+    fn render(&mut self, t_ms: u32, frame: &mut [Apa102Pixel]);
+}
+
+/// A rainbow moving along the strip
+pub struct Rainbow {
+    /// Number of times per minute the rainbow moves by a whole color wheel
+    pub bpm: u8,
+    /// Difference in hue between neighbouring leds
+    pub delta_hue: u8,
+}
+
+impl Effect for Rainbow {
+    fn render(&mut self, t_ms: u32, frame: &mut [Apa102Pixel]) {
+        let start_hue = beat8(self.bpm, t_ms);
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let hue = start_hue.wrapping_add((i as u8).wrapping_mul(self.delta_hue));
+            *pixel = Hsv::new(hue, 255, 255).into();
+        }
+    }
+}
+
+/// A single color slowly fading in and out
+pub struct Breathe {
+    pub color: RGB8,
+    /// Number of breaths per minute
+    pub bpm: u8,
+}
+
+impl Effect for Breathe {
+    fn render(&mut self, t_ms: u32, frame: &mut [Apa102Pixel]) {
+        let brightness = beatsin8(self.bpm, 0, 255, t_ms, 0);
+        frame.fill((self.color, brightness).into());
+    }
+}
+
+/// A gradient scrolling along the strip, see `Gradient::sample`
+pub struct Scroll<'a> {
+    pub gradient: Gradient<'a>,
+    /// Number of times per minute the gradient scrolls by its whole length
+    pub bpm: u8,
+}
+
+impl Effect for Scroll<'_> {
+    fn render(&mut self, t_ms: u32, frame: &mut [Apa102Pixel]) {
+        let offset = beat8(self.bpm, t_ms);
+        let num_leds = frame.len();
+        for (pixel, sample) in frame.iter_mut().zip(self.gradient.sample(num_leds, offset)) {
+            *pixel = sample;
+        }
+    }
+}
+
+impl<const N: usize> Effect for Fire<N> {
+    /// Advances the simulation by one step per frame, independent of `t_ms`
+    fn render(&mut self, _t_ms: u32, frame: &mut [Apa102Pixel]) {
+        self.step(frame);
+    }
+}
+
+/// Renders an `Effect` into a framebuffer of `N` pixels and writes it at a
+/// steady frame rate
+///
+/// The time between frames is waited for using `delay`, so it is slightly
+/// longer than requested by the time taken to render and write a frame.
+///
+/// ```no_run
+/// # use apa102_spi::{Animator, Apa102, ClockedLedBus, Rainbow};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Delay;
+/// # impl embedded_hal::delay::DelayNs for Delay {
+/// #     fn delay_ns(&mut self, _: u32) {}
+/// # }
+/// # let (apa102, delay) = (Apa102::new(Spi), Delay);
+/// let mut animator: Animator<_, _, 60> = Animator::new(apa102, delay, 50);
+/// animator.run(&mut Rainbow { bpm: 10, delta_hue: 4 })?;
+/// # Ok::<(), ()>(())
+/// ```
+pub struct Animator<W, D, const N: usize> {
+    writer: W,
+    delay: D,
+    frame: [Apa102Pixel; N],
+    frame_time_ms: u32,
+    t_ms: u32,
+}
+
+impl<W, D, const N: usize> Animator<W, D, N>
+where
+    W: SmartLedsWrite,
+    Apa102Pixel: Into<W::Color>,
+    D: DelayNs,
+{
+    /// new constructs an animator writing `fps` frames per second to `writer`
+    pub fn new(writer: W, delay: D, fps: u32) -> Animator<W, D, N> {
+        Self {
+            writer,
+            delay,
+            frame: [Apa102Pixel::default(); N],
+            frame_time_ms: 1000 / fps.max(1),
+            t_ms: 0,
+        }
+    }
+
+    /// Render, write and wait for a single frame
+    pub fn tick<E: Effect>(&mut self, effect: &mut E) -> Result<(), W::Error> {
+        effect.render(self.t_ms, &mut self.frame);
+        self.writer.write(self.frame.iter().copied())?;
+        self.delay.delay_ms(self.frame_time_ms);
+        self.t_ms = self.t_ms.wrapping_add(self.frame_time_ms);
+        Ok(())
+    }
+
+    /// Play `effect` until writing a frame fails
+    pub fn run<E: Effect>(&mut self, effect: &mut E) -> Result<Infallible, W::Error> {
+        loop {
+            self.tick(effect)?;
+        }
+    }
+
+    /// Milliseconds passed since the animator was started
+    pub fn time_ms(&self) -> u32 {
+        self.t_ms
+    }
+
+    /// Free the owned resources consuming self
+    pub fn free(self) -> (W, D) {
+        (self.writer, self.delay)
+    }
+}
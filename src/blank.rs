@@ -0,0 +1,74 @@
+use smart_leds_trait::SmartLedsWrite;
+
+use crate::{Apa102, Apa102Pixel, ClockedLedBus, Error};
+
+/// A controller switching the strip off when dropped
+///
+/// Keeps props from staying lit in their last colors when the firmware bails
+/// out, e.g. when the controller is dropped while a panic unwinds in tests on
+/// a host. The strip is switched off with `Apa102::clear`, so the wrapped
+/// controller needs `Apa102Encoder::set_num_leds` to be set.
+pub struct BlankOnDrop<SPI>
+where
+    SPI: ClockedLedBus,
+{
+    // Only taken by `free`, so that it doesn't switch the strip off
+    apa102: Option<Apa102<SPI>>,
+}
+
+impl<SPI> BlankOnDrop<SPI>
+where
+    SPI: ClockedLedBus,
+{
+    /// Wraps an existing controller
+    ///
+    /// Fails with `Error::NumLedsUnset` if the number of leds isn't set on
+    /// `apa102`, as the strip couldn't be switched off then.
+    pub fn new(apa102: Apa102<SPI>) -> Result<BlankOnDrop<SPI>, Error<SPI::Error>> {
+        match apa102.num_leds() {
+            Some(_) => Ok(Self {
+                apa102: Some(apa102),
+            }),
+            None => Err(Error::NumLedsUnset),
+        }
+    }
+
+    /// Borrow the owned controller, e.g. to change its settings or SPI
+    pub fn apa102_mut(&mut self) -> &mut Apa102<SPI> {
+        self.apa102.as_mut().unwrap()
+    }
+
+    /// Free the owned controller consuming self, without switching the strip
+    /// off
+    pub fn free(mut self) -> Apa102<SPI> {
+        self.apa102.take().unwrap()
+    }
+}
+
+impl<SPI> SmartLedsWrite for BlankOnDrop<SPI>
+where
+    SPI: ClockedLedBus,
+{
+    type Color = Apa102Pixel;
+    type Error = SPI::Error;
+    /// Write all the items of an iterator to an apa102 strip
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), SPI::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.apa102_mut().write(iterator)
+    }
+}
+
+impl<SPI> Drop for BlankOnDrop<SPI>
+where
+    SPI: ClockedLedBus,
+{
+    fn drop(&mut self) {
+        if let Some(apa102) = &mut self.apa102 {
+            // Nothing left to report the error to
+            let _ = apa102.clear();
+        }
+    }
+}
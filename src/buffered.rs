@@ -0,0 +1,375 @@
+use core::ops::{Index, IndexMut};
+
+use crate::encoder::{position, UNMAPPED_FRAME};
+use crate::{Apa102, Apa102Encoder, Apa102Pixel, ClockedLedBus};
+
+/// A controller owning a framebuffer for a strip of `N` APA102 LEDs
+///
+/// Pixels are modified in place using `set_pixel` and transmitted all at
+/// once by calling `flush()`. The framebuffer is sized at compile time, so
+/// no heap is needed.
+///
+/// Next to the pixels it holds their encoded pixel frames. `set_pixel`
+/// encodes the pixel it sets, so `flush()` only clocks out the frames between
+/// the start and end frame. Pixels changed in any other way, e.g. through
+/// `pixels_mut` or indexing, are encoded again all together by the next
+/// `flush()`, as are all pixels while dithering or a brightness ramp change
+/// them from frame to frame.
+///
+/// The pixels are kept in their logical order, see `set_index_map`. Every
+/// method taking an index or going through the pixels in order, from
+/// `set_pixel` to `rotate_left` and `segment_mut`, uses that order.
+pub struct Apa102Buffered<SPI, const N: usize> {
+    apa102: Apa102<SPI>,
+    // In logical order, only the first `len()` are used
+    pixels: [Apa102Pixel; N],
+    frames: [[u8; 4]; N],
+    // Whether `frames` may be out of date with `pixels` or the settings
+    stale: bool,
+    index_map: Option<&'static [usize]>,
+    // The pixels last written by `flush`, `None` if the strip may show
+    // something else
+    written: Option<[Apa102Pixel; N]>,
+}
+
+impl<SPI, const N: usize> Apa102Buffered<SPI, N>
+where
+    SPI: ClockedLedBus,
+{
+    /// Wraps an existing controller, starting with all pixels turned off
+    pub fn new(apa102: Apa102<SPI>) -> Apa102Buffered<SPI, N> {
+        Self {
+            apa102,
+            pixels: [Apa102Pixel::default(); N],
+            frames: [[0; 4]; N],
+            stale: true,
+            index_map: None,
+            written: None,
+        }
+    }
+
+    /// Set where each pixel is placed on the strip, like
+    /// `Apa102Device::set_index_map`
+    ///
+    /// The framebuffer then holds one pixel per entry of `index_map`, at
+    /// most `N`, and the pixels already set keep their index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a position in `index_map` is not smaller than `N`.
+    pub fn set_index_map(&mut self, index_map: Option<&'static [usize]>) {
+        if let Some(index_map) = index_map {
+            assert!(
+                index_map.iter().all(|&position| position < N),
+                "index map past the end of the strip"
+            );
+        }
+        self.index_map = index_map;
+        self.stale = true;
+        self.written = None;
+    }
+
+    /// The number of pixels in the framebuffer, `N` or the length of the
+    /// index map if shorter
+    pub fn len(&self) -> usize {
+        self.index_map.map_or(N, |index_map| index_map.len().min(N))
+    }
+
+    /// Whether the framebuffer holds no pixels
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Set the pixel at `index`, from an `Apa102Pixel` or a plain `RGB8`
+    ///
+    /// The change only shows up on the strip after the next `flush()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not smaller than `len()`.
+    pub fn set_pixel(&mut self, index: usize, pixel: impl Into<Apa102Pixel>) {
+        assert!(index < self.len(), "index past the end of the framebuffer");
+        let pixel = pixel.into();
+        self.pixels[index] = pixel;
+        if let Some(position) = position(self.index_map, index) {
+            self.frames[position] = self.apa102.encoder.encode_pixel_at(position, pixel);
+        }
+    }
+
+    /// The pixel at `index`, as last set, `None` if out of range
+    pub fn pixel(&self, index: usize) -> Option<Apa102Pixel> {
+        self.pixels().get(index).copied()
+    }
+
+    /// Borrow the pixels of the segment called `name`, to render it on its
+    /// own
+    ///
+    /// The segments are the ones set with `Apa102Encoder::set_segments`.
+    ///
+    /// All segments are written together by the next `flush()`. Returns
+    /// `None` if there is no such segment or it doesn't fit in the
+    /// framebuffer. Like everywhere else in the framebuffer, the range of the
+    /// segment is taken as indices before the index map.
+    ///
+    pub fn segment_mut(&mut self, name: &str) -> Option<&mut [Apa102Pixel]> {
+        let segment = self
+            .apa102
+            .encoder
+            .segments()
+            .iter()
+            .find(|segment| segment.name() == name)?;
+        self.pixels_mut().get_mut(segment.range())
+    }
+
+    /// The pixels of the framebuffer
+    pub fn pixels(&self) -> &[Apa102Pixel] {
+        &self.pixels[..self.len()]
+    }
+
+    /// Borrow the whole framebuffer, e.g. to split it with `split_segments`
+    ///
+    /// Every change to the pixels other than through `set_pixel` goes
+    /// through here, marking their encoded frames as out of date.
+    pub fn pixels_mut(&mut self) -> &mut [Apa102Pixel] {
+        self.stale = true;
+        let len = self.len();
+        &mut self.pixels[..len]
+    }
+
+    /// Iterate over the pixels of the framebuffer
+    pub fn iter(&self) -> impl Iterator<Item = &Apa102Pixel> {
+        self.pixels().iter()
+    }
+
+    /// Iterate mutably over the pixels of the framebuffer, e.g. to apply an
+    /// effect in place before the next `flush()`
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Apa102Pixel> {
+        self.pixels_mut().iter_mut()
+    }
+
+    /// Move every pixel `n` indices down, the first ones wrapping around to
+    /// the end
+    pub fn rotate_left(&mut self, n: usize) {
+        let pixels = self.pixels_mut();
+        pixels.rotate_left(n.checked_rem(pixels.len()).unwrap_or(0));
+    }
+
+    /// Move every pixel `n` indices up, the last ones wrapping around to the
+    /// start
+    pub fn rotate_right(&mut self, n: usize) {
+        let pixels = self.pixels_mut();
+        pixels.rotate_right(n.checked_rem(pixels.len()).unwrap_or(0));
+    }
+
+    /// Move every pixel one index up, dropping the last one, and set the
+    /// first one to `pixel`
+    ///
+    /// Shifting in a pixel per frame, and black ones after it, moves a comet
+    /// along the strip.
+    pub fn shift_in(&mut self, pixel: impl Into<Apa102Pixel>) {
+        let pixels = self.pixels_mut();
+        if let Some(last) = pixels.len().checked_sub(1) {
+            pixels.copy_within(..last, 1);
+            pixels[0] = pixel.into();
+        }
+    }
+
+    /// Write the whole framebuffer to the strip
+    pub fn flush(&mut self) -> Result<(), SPI::Error> {
+        self.written = None;
+        let len = self.len();
+        let encoder = &mut self.apa102.encoder;
+        // Frames encoded while the encoding still varied are out of date
+        // even if it stops varying with this frame
+        let varies = encoder.varies_per_frame();
+        encoder.next_frame();
+        if self.stale || varies {
+            if self.index_map.is_some() {
+                self.frames = [UNMAPPED_FRAME; N];
+            }
+            for (index, pixel) in self.pixels[..len].iter().enumerate() {
+                if let Some(position) = position(self.index_map, index) {
+                    self.frames[position] = encoder.encode_pixel_at(position, *pixel);
+                }
+            }
+            self.stale = false;
+        }
+        encoder.write_encoded(&mut self.apa102.spi, &self.frames)?;
+        self.written = Some(self.pixels);
+        Ok(())
+    }
+
+    /// Write the framebuffer to the strip only if it changed since the last
+    /// `flush()`, returning whether it was written
+    ///
+    /// Saves bus time and power on mostly static displays. Changing the
+    /// settings through `apa102_mut` makes the next call write the frame
+    /// again. Effects applied by the controller on every frame, like
+    /// dithering and the brightness ramp, don't progress while nothing is
+    /// written.
+    pub fn flush_if_changed(&mut self) -> Result<bool, SPI::Error> {
+        if self.written == Some(self.pixels) {
+            return Ok(false);
+        }
+        self.flush()?;
+        Ok(true)
+    }
+
+    /// Turn all pixels of the framebuffer off and write it to the strip
+    pub fn clear(&mut self) -> Result<(), SPI::Error> {
+        self.pixels_mut().fill(Apa102Pixel::default());
+        self.flush()
+    }
+
+    /// Write an already encoded frame, bypassing the framebuffer
+    ///
+    /// See `Apa102::write_raw_frame`.
+    pub fn write_raw_frame(&mut self, frame: &[u8]) -> Result<(), SPI::Error> {
+        self.written = None;
+        self.apa102.write_raw_frame(frame)
+    }
+
+    /// The encoder holding the settings of the owned controller
+    pub fn encoder(&self) -> &Apa102Encoder {
+        &self.apa102.encoder
+    }
+
+    /// Borrow the encoder to change the settings of the owned controller,
+    /// e.g. `leds.encoder_mut().set_segments(&SEGMENTS)`
+    pub fn encoder_mut(&mut self) -> &mut Apa102Encoder {
+        self.stale = true;
+        self.written = None;
+        &mut self.apa102.encoder
+    }
+
+    /// Borrow the owned controller, e.g. to change its settings or SPI
+    pub fn apa102_mut(&mut self) -> &mut Apa102<SPI> {
+        self.stale = true;
+        self.written = None;
+        &mut self.apa102
+    }
+
+    /// Free the owned controller consuming self
+    pub fn free(self) -> Apa102<SPI> {
+        self.apa102
+    }
+}
+
+/// Pixels can be read and set with `strip[index]`, like `pixel` and
+/// `set_pixel` but panicking when out of range
+impl<SPI, const N: usize> Index<usize> for Apa102Buffered<SPI, N>
+where
+    SPI: ClockedLedBus,
+{
+    type Output = Apa102Pixel;
+
+    fn index(&self, index: usize) -> &Apa102Pixel {
+        &self.pixels()[index]
+    }
+}
+
+impl<SPI, const N: usize> IndexMut<usize> for Apa102Buffered<SPI, N>
+where
+    SPI: ClockedLedBus,
+{
+    fn index_mut(&mut self, index: usize) -> &mut Apa102Pixel {
+        &mut self.pixels_mut()[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use smart_leds_trait::RGB8;
+
+    use super::*;
+    use crate::test_util::RecordingBus;
+    use crate::{PixelOrder, Segment};
+
+    const RED: [u8; 4] = [0xFF, 0, 0, 0xFF];
+    const OFF: [u8; 4] = [0xFF, 0, 0, 0];
+
+    fn buffered<const N: usize>() -> Apa102Buffered<RecordingBus, N> {
+        Apa102Buffered::new(Apa102::new(RecordingBus::default()))
+    }
+
+    /// The pixel frames of every flush, between the 4 byte start and end
+    /// frames
+    fn frames<const N: usize>(buffered: Apa102Buffered<RecordingBus, N>) -> Vec<Vec<[u8; 4]>> {
+        let bytes = buffered.free().free().bytes;
+        let frame_len = 4 + 4 * N + 4;
+        assert_eq!(bytes.len() % frame_len, 0);
+        bytes
+            .chunks(frame_len)
+            .map(|frame| {
+                let pixels = frame[4..4 + 4 * N].chunks(4);
+                pixels.map(|pixel| pixel.try_into().unwrap()).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flushes_set_pixels() {
+        let mut strip = buffered::<2>();
+        strip.set_pixel(1, RGB8::new(255, 0, 0));
+        strip.flush().unwrap();
+        assert_eq!(frames(strip), [[OFF, RED]]);
+    }
+
+    #[test]
+    fn clear_turns_off_flushed_pixels() {
+        let mut strip = buffered::<2>();
+        strip.set_pixel(0, RGB8::new(255, 0, 0));
+        strip.flush().unwrap();
+        strip.clear().unwrap();
+        assert_eq!(frames(strip), [[RED, OFF], [OFF, OFF]]);
+    }
+
+    #[test]
+    fn reencodes_pixels_changed_in_place() {
+        let mut strip = buffered::<2>();
+        strip.flush().unwrap();
+        strip[1] = RGB8::new(255, 0, 0).into();
+        strip.flush().unwrap();
+        strip.rotate_left(1);
+        strip.flush().unwrap();
+        strip.pixels_mut()[0] = Apa102Pixel::default();
+        strip.flush().unwrap();
+        assert_eq!(
+            frames(strip),
+            [[OFF, OFF], [OFF, RED], [RED, OFF], [OFF, OFF]]
+        );
+    }
+
+    #[test]
+    fn renders_segments_of_the_encoder() {
+        const SEGMENTS: [Segment; 2] = [
+            Segment::new("status", 0, 1),
+            Segment::new("ring", 1, 2).with_pixel_order(PixelOrder::RGB),
+        ];
+        let mut strip = buffered::<3>();
+        strip.encoder_mut().set_segments(&SEGMENTS);
+        strip.segment_mut("ring").unwrap()[1] = RGB8::new(255, 0, 0).into();
+        assert!(strip.segment_mut("dial").is_none());
+        strip.flush().unwrap();
+        assert_eq!(frames(strip), [[OFF, OFF, [0xFF, 0xFF, 0, 0]]]);
+    }
+
+    #[test]
+    fn keeps_pixels_in_logical_order() {
+        const UNMAPPED: [u8; 4] = [0xE0, 0, 0, 0];
+        let mut strip = buffered::<3>();
+        strip.set_index_map(Some(&[2, 0]));
+        assert_eq!(strip.len(), 2);
+        assert_eq!(strip.pixel(2), None);
+        strip.set_pixel(0, RGB8::new(255, 0, 0));
+        strip.flush().unwrap();
+        strip.rotate_left(1);
+        assert_eq!(strip.iter().position(|pixel| pixel.color.r == 255), Some(1));
+        assert_eq!(strip[1].color, RGB8::new(255, 0, 0));
+        strip.flush().unwrap();
+        assert_eq!(frames(strip), [[OFF, UNMAPPED, RED], [RED, UNMAPPED, OFF]]);
+    }
+}
@@ -0,0 +1,251 @@
+use core::convert::Infallible;
+
+use embedded_hal::spi::SpiDevice;
+
+use smart_leds_trait::RGB8;
+
+use crate::{
+    Apa102, Apa102Device, Apa102Encoder, Apa102Pixel, BrightnessCurve, BrightnessStrategy,
+    ChipVariant, ClockedLedBus, ColorTemperature, Derating, Error, Gamma, PixelOrder, Segment,
+    TemperatureCompensation, U5,
+};
+
+/// Builds an `Apa102`, `Apa102Device` or `Apa102Encoder`
+///
+/// Every option starts out with the same default as `Apa102::new`:
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102Builder, ChipVariant, ClockedLedBus, PixelOrder};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let spi = Spi;
+/// let apa102 = Apa102Builder::new()
+///     .chip_variant(ChipVariant::Sk9822)
+///     .pixel_order(PixelOrder::GRB)
+///     .brightness(64)
+///     .build(spi)?;
+/// # Ok::<(), apa102_spi::Error<()>>(())
+/// ```
+///
+/// The options are checked when building, which fails with
+/// `Error::ConflictingOptions` if some of them contradict each other, see
+/// there.
+#[derive(Clone)]
+pub struct Apa102Builder {
+    encoder: Apa102Encoder,
+}
+
+impl Apa102Builder {
+    pub fn new() -> Apa102Builder {
+        Self {
+            encoder: Apa102Encoder::new(),
+        }
+    }
+
+    /// See `Apa102Encoder::set_start_frame_length`
+    pub fn start_frame_length(mut self, start_frame_length: u8) -> Self {
+        self.encoder.set_start_frame_length(start_frame_length);
+        self
+    }
+
+    /// See `Apa102Encoder::set_end_frame_length`
+    pub fn end_frame_length(mut self, end_frame_length: u8) -> Self {
+        self.encoder.set_end_frame_length(end_frame_length);
+        self
+    }
+
+    /// See `Apa102Encoder::set_invert_end_frame`
+    pub fn invert_end_frame(mut self, invert_end_frame: bool) -> Self {
+        self.encoder.set_invert_end_frame(invert_end_frame);
+        self
+    }
+
+    /// See `Apa102Encoder::set_extra_end_frame_length`
+    pub fn extra_end_frame_length(mut self, extra_end_frame_length: u8) -> Self {
+        self.encoder
+            .set_extra_end_frame_length(extra_end_frame_length);
+        self
+    }
+
+    /// See `Apa102Encoder::set_end_frame_multiplier`
+    pub fn end_frame_multiplier(mut self, end_frame_multiplier: u8) -> Self {
+        self.encoder.set_end_frame_multiplier(end_frame_multiplier);
+        self
+    }
+
+    /// See `Apa102Encoder::set_pixel_order`
+    pub fn pixel_order(mut self, pixel_order: PixelOrder) -> Self {
+        self.encoder.set_pixel_order(pixel_order);
+        self
+    }
+
+    /// See `Apa102Encoder::set_chip_variant`
+    pub fn chip_variant(mut self, chip_variant: ChipVariant) -> Self {
+        self.encoder.set_chip_variant(chip_variant);
+        self
+    }
+
+    /// See `Apa102Encoder::set_brightness`
+    pub fn brightness(mut self, brightness: u8) -> Self {
+        self.encoder.set_brightness(brightness);
+        self
+    }
+
+    /// Overrides the strategy preferred by the chip variant
+    pub fn brightness_strategy(mut self, brightness_strategy: BrightnessStrategy) -> Self {
+        self.encoder.set_brightness_strategy(brightness_strategy);
+        self
+    }
+
+    /// See `Apa102Encoder::set_brightness_curve`
+    pub fn brightness_curve(mut self, brightness_curve: BrightnessCurve) -> Self {
+        self.encoder.set_brightness_curve(brightness_curve);
+        self
+    }
+
+    /// See `Apa102Encoder::set_brightness_ramp`
+    pub fn brightness_ramp(mut self, brightness_ramp: Option<u8>) -> Self {
+        self.encoder.set_brightness_ramp(brightness_ramp);
+        self
+    }
+
+    /// See `Apa102Encoder::set_dithering`
+    pub fn dithering(mut self, dithering: bool) -> Self {
+        self.encoder.set_dithering(dithering);
+        self
+    }
+
+    /// See `Apa102Encoder::set_video_scaling`
+    pub fn video_scaling(mut self, video_scaling: bool) -> Self {
+        self.encoder.set_video_scaling(video_scaling);
+        self
+    }
+
+    /// See `Apa102Encoder::set_derating`
+    pub fn derating(mut self, derating: Option<Derating>) -> Self {
+        self.encoder.set_derating(derating);
+        self
+    }
+
+    /// See `Apa102Encoder::set_temperature_compensation`
+    pub fn temperature_compensation(
+        mut self,
+        temperature_compensation: Option<TemperatureCompensation>,
+    ) -> Self {
+        self.encoder
+            .set_temperature_compensation(temperature_compensation);
+        self
+    }
+
+    /// See `Apa102Encoder::set_calibration`
+    pub fn calibration(mut self, calibration: Option<&'static [RGB8]>) -> Self {
+        self.encoder.set_calibration(calibration);
+        self
+    }
+
+    /// See `Apa102Encoder::set_segments`
+    pub fn segments(mut self, segments: &'static [Segment]) -> Self {
+        self.encoder.set_segments(segments);
+        self
+    }
+
+    /// See `Apa102Encoder::set_global_current`
+    pub fn global_current(mut self, global_current: U5) -> Self {
+        self.encoder.set_global_current(Some(global_current));
+        self
+    }
+
+    /// See `Apa102Encoder::set_five_bit_floor`
+    pub fn five_bit_floor(mut self, five_bit_floor: u8) -> Self {
+        self.encoder.set_five_bit_floor(five_bit_floor);
+        self
+    }
+
+    /// See `Apa102Encoder::set_num_leds`
+    pub fn num_leds(mut self, num_leds: usize) -> Self {
+        self.encoder.set_num_leds(Some(num_leds));
+        self
+    }
+
+    /// See `Apa102Encoder::set_fit_to_num_leds`
+    pub fn fit_to_num_leds(mut self, fit_to_num_leds: bool) -> Self {
+        self.encoder.set_fit_to_num_leds(fit_to_num_leds);
+        self
+    }
+
+    /// See `Apa102Encoder::set_color_conversion`
+    pub fn color_conversion(mut self, color_conversion: fn(RGB8) -> Apa102Pixel) -> Self {
+        self.encoder.set_color_conversion(Some(color_conversion));
+        self
+    }
+
+    /// See `Apa102Encoder::set_gamma`
+    pub fn gamma(mut self, gamma: Gamma) -> Self {
+        self.encoder.set_gamma(Some(gamma));
+        self
+    }
+
+    /// See `Apa102Encoder::set_color_correction`
+    pub fn color_correction(mut self, color_correction: RGB8) -> Self {
+        self.encoder.set_color_correction(color_correction);
+        self
+    }
+
+    /// See `Apa102Encoder::set_color_temperature`
+    pub fn color_temperature(mut self, color_temperature: ColorTemperature) -> Self {
+        self.encoder.set_color_temperature(color_temperature);
+        self
+    }
+
+    /// Build a standalone encoder
+    pub fn build_encoder(&self) -> Result<Apa102Encoder, Error<Infallible>> {
+        self.checked_encoder()
+    }
+
+    /// Build a controller for an `SpiBus` or other `ClockedLedBus`
+    pub fn build<SPI>(&self, spi: SPI) -> Result<Apa102<SPI>, Error<SPI::Error>>
+    where
+        SPI: ClockedLedBus,
+    {
+        Ok(Apa102::with_encoder(spi, self.checked_encoder()?))
+    }
+
+    /// Build a controller for an `SpiDevice`, see `Apa102Device`
+    ///
+    /// Fails with `Error::BufferTooSmall` if the number of leds is set and a
+    /// frame for all of them doesn't fit into `buffer`.
+    pub fn build_device<'a, SPI>(
+        &self,
+        spi: SPI,
+        buffer: &'a mut [u8],
+    ) -> Result<Apa102Device<'a, SPI>, Error<SPI::Error>>
+    where
+        SPI: SpiDevice,
+    {
+        let encoder = self.checked_encoder()?;
+        if let Some(num_leds) = encoder.num_leds() {
+            if buffer.len() < encoder.frame_len(num_leds) {
+                return Err(Error::BufferTooSmall);
+            }
+        }
+        Ok(Apa102Device::with_encoder(spi, buffer, encoder))
+    }
+
+    fn checked_encoder<E>(&self) -> Result<Apa102Encoder, Error<E>> {
+        match self.encoder.has_conflicting_options() {
+            true => Err(Error::ConflictingOptions),
+            false => Ok(self.encoder.clone()),
+        }
+    }
+}
+
+impl Default for Apa102Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
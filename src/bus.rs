@@ -0,0 +1,24 @@
+use embedded_hal::spi::SpiBus;
+
+/// A transport clocking out bytes to a strip of clocked leds
+///
+/// Implemented for every `SpiBus`. Implement it for other peripherals, like
+/// RP2040 PIO, i.MX RT FlexIO or I2S, to drive leds with `Apa102` over them.
+/// For an `SpiDevice` use `Apa102Device` instead.
+pub trait ClockedLedBus {
+    type Error;
+
+    /// Clock out all of `bytes`
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl<SPI> ClockedLedBus for SPI
+where
+    SPI: SpiBus,
+{
+    type Error = SPI::Error;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SPI::Error> {
+        self.write(bytes)
+    }
+}
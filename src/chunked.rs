@@ -0,0 +1,58 @@
+use crate::ClockedLedBus;
+
+/// Size of the stack buffer frames are collected in before being written out
+const CHUNK_SIZE: usize = 128;
+
+/// Batches bytes into chunks to avoid the overhead of one transaction per led
+pub(crate) struct Chunked<'a, B> {
+    bus: &'a mut B,
+    chunk: [u8; CHUNK_SIZE],
+    len: usize,
+}
+
+impl<'a, B> Chunked<'a, B>
+where
+    B: ClockedLedBus,
+{
+    pub(crate) fn new(bus: &'a mut B) -> Self {
+        Self {
+            bus,
+            chunk: [0x00; CHUNK_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Append a pixel frame, which needs to be shorter than a chunk
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Result<(), B::Error> {
+        if self.len + bytes.len() > CHUNK_SIZE {
+            self.bus.write_bytes(&self.chunk[..self.len])?;
+            self.len = 0;
+        }
+        self.chunk[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    /// Append `count` copies of `byte`, e.g. for start and end frames
+    pub(crate) fn fill(&mut self, byte: u8, mut count: usize) -> Result<(), B::Error> {
+        while count > 0 {
+            if self.len == CHUNK_SIZE {
+                self.bus.write_bytes(&self.chunk)?;
+                self.len = 0;
+            }
+            let n = count.min(CHUNK_SIZE - self.len);
+            self.chunk[self.len..self.len + n].fill(byte);
+            self.len += n;
+            count -= n;
+        }
+        Ok(())
+    }
+
+    /// Write out whatever is left
+    pub(crate) fn finish(self) -> Result<(), B::Error> {
+        if self.len > 0 {
+            self.bus.write_bytes(&self.chunk[..self.len])?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+//! In place operations on frames of pixels, ported from FastLED
+//!
+//! Dimming is done on the brightness of the pixels rather than on their
+//! colors, so it goes through the `BrightnessCurve` and `BrightnessStrategy`
+//! of the controller like any other brightness, and the colors keep their
+//! full resolution.
+
+use smart_leds_trait::{RGB16, RGB8};
+
+use crate::math::{lerp_rgb8, scale8};
+use crate::Apa102Pixel;
+
+/// Dim every pixel by `scale / 256`, with a `scale` of 255 keeping them
+/// unchanged
+pub fn nscale8(pixels: &mut [Apa102Pixel], scale: u8) {
+    for pixel in pixels {
+        pixel.brightness = scale8(pixel.brightness, scale);
+    }
+}
+
+/// Dim every pixel by `amount / 256`, e.g. every frame to leave trails behind
+/// moving pixels
+pub fn fade_to_black_by(pixels: &mut [Apa102Pixel], amount: u8) {
+    nscale8(pixels, 255 - amount);
+}
+
+/// Spread the light of every pixel to its neighbours, `amount` being the
+/// share that leaves a pixel
+///
+/// Neighbours are mixed by the light they emit, their colors multiplied by
+/// their brightness, so a dim pixel doesn't bleed as much as a bright one of
+/// the same color. Like in FastLED, light spreading past the ends of the
+/// strip is lost.
+pub fn blur1d(pixels: &mut [Apa102Pixel], amount: u8) {
+    let keep = 255 - amount;
+    let seep = amount >> 1;
+    // The share the previous pixel gives to the current one, and its own
+    // light, only written once it got its share of the current pixel
+    let mut carryover = [0; 3];
+    let mut previous: Option<[u32; 3]> = None;
+    for i in 0..pixels.len() {
+        let light = light(pixels[i]);
+        let part = light.map(|c| scale(c, seep));
+        let mut current = light.map(|c| scale(c, keep));
+        for channel in 0..3 {
+            current[channel] += carryover[channel];
+        }
+        if let Some(mut previous) = previous {
+            for channel in 0..3 {
+                previous[channel] += part[channel];
+            }
+            pixels[i - 1] = from_light(previous);
+        }
+        previous = Some(current);
+        carryover = part;
+    }
+    if let (Some(previous), Some(last)) = (previous, pixels.last_mut()) {
+        *last = from_light(previous);
+    }
+}
+
+/// Mix two colors channel by channel, `amount` going from 0 for `a` to 255
+/// for `b`
+pub fn blend_rgb8(a: RGB8, b: RGB8, amount: u8) -> RGB8 {
+    lerp_rgb8(a, b, amount)
+}
+
+/// Mix two pixels, `amount` going from 0 for `a` to 255 for `b`
+///
+/// The pixels are mixed by the light they emit, their colors multiplied by
+/// their brightness, rather than mixing colors and brightness separately.
+/// The result emits the mix of the light of both, no matter how it is split
+/// between color and brightness, so crossfades don't dip or overshoot.
+pub fn blend(a: Apa102Pixel, b: Apa102Pixel, amount: u8) -> Apa102Pixel {
+    if a.brightness == b.brightness {
+        // Mixing the colors gives the same light, keeping the brightness
+        let color = lerp_rgb8(a.color, b.color, amount);
+        return Apa102Pixel::from((color, a.brightness));
+    }
+    let (a, b) = (light(a), light(b));
+    let mut mixed = [0; 3];
+    for channel in 0..3 {
+        let (from, to) = (a[channel] as i32, b[channel] as i32);
+        mixed[channel] = (from + (to - from) * amount as i32 / 255) as u32;
+    }
+    from_light(mixed)
+}
+
+/// Crossfade two frames into `out`, `amount` going from 0 for `a` to 255 for
+/// `b`
+///
+/// Each pixel is mixed by the light it emits, see `blend`, so fading between
+/// frames with different brightness doesn't flash. Stops at the end of the
+/// shortest of the three.
+pub fn blend_frames(a: &[Apa102Pixel], b: &[Apa102Pixel], amount: u8, out: &mut [Apa102Pixel]) {
+    for ((a, b), out) in a.iter().zip(b).zip(out) {
+        *out = blend(*a, *b, amount);
+    }
+}
+
+/// Light emitted by a pixel, its color channels multiplied by its brightness
+fn light(pixel: Apa102Pixel) -> [u32; 3] {
+    let channel = |c: u8| c as u32 * pixel.brightness as u32;
+    [
+        channel(pixel.color.r),
+        channel(pixel.color.g),
+        channel(pixel.color.b),
+    ]
+}
+
+fn from_light(light: [u32; 3]) -> Apa102Pixel {
+    let [r, g, b] = light.map(|c| c.min(255 * 255) as u16);
+    RGB16::new(r, g, b).into()
+}
+
+/// Like `scale8` for light values
+fn scale(value: u32, scale: u8) -> u32 {
+    (value * (scale as u32 + 1)) >> 8
+}
@@ -0,0 +1,66 @@
+//! The building blocks used to encode pixels, for renderers producing raw
+//! frames themselves
+//!
+//! Brightness is given as 8 bit value throughout, 255 being full brightness.
+//! The 5 bit values are the brightness field of a pixel frame, without the
+//! three leading one bits.
+
+use smart_leds_trait::RGB8;
+
+pub use crate::math::{scale8, scale8_video};
+
+/// Convert an 8 bit brightness to the 5 bit brightness field
+///
+/// Rounds up, so a non-zero brightness never turns the led off.
+pub fn five_bit(brightness: u8) -> u8 {
+    (brightness as u16 * 31).div_ceil(255) as u8
+}
+
+/// Convert an 8 bit brightness to the 5 bit brightness field, dithering
+/// between the two nearest values
+///
+/// The higher value is chosen when the fraction between them is above
+/// `phase`. Passing a different phase every frame, evenly spread over the
+/// range of `u8`, makes the average light output match `brightness`. Like
+/// `five_bit`, a non-zero brightness never turns the led off.
+pub fn five_bit_dithered(brightness: u8, phase: u8) -> u8 {
+    // The exact 5 bit value, with 8 fractional bits
+    let exact = brightness as u32 * 31 * 256 / 255;
+    let level = (exact >> 8) as u8 + u8::from(exact as u8 > phase);
+    if brightness > 0 {
+        level.max(1)
+    } else {
+        0
+    }
+}
+
+/// Apply `brightness` to the color channels, see `scale8`
+pub fn scale_color(color: RGB8, brightness: u8) -> RGB8 {
+    let scale = |c: u8| scale8(c, brightness);
+    RGB8::new(scale(color.r), scale(color.g), scale(color.b))
+}
+
+/// Apply `brightness` to the color channels, see `scale8_video`
+pub fn scale_color_video(color: RGB8, brightness: u8) -> RGB8 {
+    let scale = |c: u8| scale8_video(c, brightness);
+    RGB8::new(scale(color.r), scale(color.g), scale(color.b))
+}
+
+/// Split a color dimmed by `brightness` into a 5 bit brightness and color
+/// channels, for up to 13 bits of resolution
+///
+/// The light output is proportional to the product of both, so the lowest
+/// brightness the brightest channel still fits in keeps the most color
+/// resolution.
+pub fn pseudo13(color: RGB8, brightness: u8) -> (u8, RGB8) {
+    // Target output of a channel with 8 fractional bits, relative to a
+    // brightness field of 1
+    let target = |c: u8| c as u32 * brightness as u32 * 31 * 256 / 255;
+    let max = target(color.r.max(color.g).max(color.b));
+    let five_bit = max.div_ceil(256 * 255).max(1);
+    let channel = |c: u8| ((target(c) + five_bit * 128) / (five_bit * 256)) as u8;
+    (
+        five_bit as u8,
+        RGB8::new(channel(color.r), channel(color.g), channel(color.b)),
+    )
+}
@@ -0,0 +1,129 @@
+use smart_leds_trait::RGB8;
+
+use crate::math::lerp_rgb8;
+
+/// Color correction for typical SMD5050 leds, as used on most strips, to be
+/// passed to `set_color_correction`
+///
+/// Like the other presets, this matches the value of FastLED.
+pub const TYPICAL_SMD5050: RGB8 = RGB8::new(0xFF, 0xB0, 0xF0);
+/// Color correction for typical led strips, the same as `TYPICAL_SMD5050`
+pub const TYPICAL_LED_STRIP: RGB8 = RGB8::new(0xFF, 0xB0, 0xF0);
+/// Color correction for typical 8mm through hole "pixels"
+pub const TYPICAL_8MM_PIXEL: RGB8 = RGB8::new(0xFF, 0xE0, 0x8C);
+/// Color correction for typical pixel strings, the same as
+/// `TYPICAL_8MM_PIXEL`
+pub const TYPICAL_PIXEL_STRING: RGB8 = RGB8::new(0xFF, 0xE0, 0x8C);
+/// Leaves the colors unchanged, the default
+pub const UNCORRECTED: RGB8 = RGB8::new(0xFF, 0xFF, 0xFF);
+
+/// The color of a light source, used to make white leds match it
+///
+/// The presets match the ones of FastLED. Each is applied like a color
+/// correction, scaling down the channels the light source is weaker in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorTemperature(pub RGB8);
+
+impl ColorTemperature {
+    /// 1900 Kelvin
+    pub const CANDLE: ColorTemperature = Self::from_packed(0xFF9329);
+    /// 2600 Kelvin
+    pub const TUNGSTEN_40W: ColorTemperature = Self::from_packed(0xFFC58F);
+    /// 2850 Kelvin
+    pub const TUNGSTEN_100W: ColorTemperature = Self::from_packed(0xFFD6AA);
+    /// 3200 Kelvin
+    pub const HALOGEN: ColorTemperature = Self::from_packed(0xFFF1E0);
+    /// 5200 Kelvin
+    pub const CARBON_ARC: ColorTemperature = Self::from_packed(0xFFFAF4);
+    /// 5400 Kelvin
+    pub const HIGH_NOON_SUN: ColorTemperature = Self::from_packed(0xFFFFFB);
+    /// 6000 Kelvin
+    pub const DIRECT_SUNLIGHT: ColorTemperature = Self::from_packed(0xFFFFFF);
+    /// 7000 Kelvin
+    pub const OVERCAST_SKY: ColorTemperature = Self::from_packed(0xC9E2FF);
+    /// 20000 Kelvin
+    pub const CLEAR_BLUE_SKY: ColorTemperature = Self::from_packed(0x409CFF);
+    pub const WARM_FLUORESCENT: ColorTemperature = Self::from_packed(0xFFF4E5);
+    pub const STANDARD_FLUORESCENT: ColorTemperature = Self::from_packed(0xF4FFFA);
+    pub const COOL_WHITE_FLUORESCENT: ColorTemperature = Self::from_packed(0xD4EBFF);
+    pub const FULL_SPECTRUM_FLUORESCENT: ColorTemperature = Self::from_packed(0xFFF4F2);
+    pub const GROW_LIGHT_FLUORESCENT: ColorTemperature = Self::from_packed(0xFFEFF7);
+    pub const BLACK_LIGHT_FLUORESCENT: ColorTemperature = Self::from_packed(0xA700FF);
+    pub const MERCURY_VAPOR: ColorTemperature = Self::from_packed(0xD8F7FF);
+    pub const SODIUM_VAPOR: ColorTemperature = Self::from_packed(0xFFD1B2);
+    pub const METAL_HALIDE: ColorTemperature = Self::from_packed(0xF2FCFF);
+    pub const HIGH_PRESSURE_SODIUM: ColorTemperature = Self::from_packed(0xFFB74C);
+    /// Leaves the colors unchanged, the default
+    pub const UNCORRECTED: ColorTemperature = Self::from_packed(0xFFFFFF);
+
+    const fn from_packed(color: u32) -> ColorTemperature {
+        let [_, r, g, b] = color.to_be_bytes();
+        ColorTemperature(RGB8 { r, g, b })
+    }
+}
+
+impl Default for ColorTemperature {
+    fn default() -> Self {
+        Self::UNCORRECTED
+    }
+}
+
+/// A curve of color corrections over the temperature of the leds
+///
+/// The efficiency of leds drops as they heat up, red more so than green and
+/// blue, so the white point of dense strips shifts while running. Each
+/// keyframe is a temperature in °C and the correction to apply on top of the
+/// color correction at it. The keyframes have to be sorted by temperature.
+/// Corrections are mixed linearly between keyframes and held before the
+/// first and after the last one.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, ClockedLedBus, TemperatureCompensation};
+/// # use smart_leds_trait::RGB8;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # fn read_temperature() -> i16 {
+/// #     40
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// const COMPENSATION: TemperatureCompensation = TemperatureCompensation::new(&[
+///     (25, RGB8::new(0xE6, 0xFF, 0xFF)),
+///     (70, RGB8::new(0xFF, 0xE6, 0xE0)),
+/// ]);
+/// apa102.encoder_mut().set_temperature_compensation(Some(COMPENSATION));
+/// apa102.encoder_mut().set_led_temperature_c(read_temperature());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct TemperatureCompensation {
+    keyframes: &'static [(i16, RGB8)],
+}
+
+impl TemperatureCompensation {
+    pub const fn new(keyframes: &'static [(i16, RGB8)]) -> Self {
+        Self { keyframes }
+    }
+
+    /// Correction at `temperature_c`, uncorrected if there are no keyframes
+    pub fn correction_at(&self, temperature_c: i16) -> RGB8 {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|&(keyframe, _)| keyframe >= temperature_c);
+        match next {
+            None => self.keyframes.last().map_or(UNCORRECTED, |k| k.1),
+            Some(0) => self.keyframes[0].1,
+            Some(i) => {
+                let (start, from) = self.keyframes[i - 1];
+                let (end, to) = self.keyframes[i];
+                let amount =
+                    (temperature_c as i32 - start as i32) * 255 / (end as i32 - start as i32);
+                lerp_rgb8(from, to, amount as u8)
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use smart_leds_trait::RGB8;
+
+use crate::Apa102Pixel;
+
+const VERSION_MASK: u8 = 0xC0;
+const VERSION_1: u8 = 0x40;
+const TIMECODE: u8 = 0x10;
+const PUSH: u8 = 0x01;
+
+/// A Distributed Display Protocol data packet, as sent over UDP by WLED,
+/// xLights and others
+///
+/// Frames too large for a single packet are split over several packets,
+/// each holding the data starting at `offset`. The last one has the push
+/// flag set, telling the receiver to show the assembled frame.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, Apa102Pixel, ClockedLedBus, DdpPacket};
+/// # use smart_leds_trait::SmartLedsWrite;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # #[derive(Debug)]
+/// # enum MyError {
+/// #     InvalidPacket,
+/// #     Spi,
+/// # }
+/// # impl From<()> for MyError {
+/// #     fn from(_: ()) -> Self {
+/// #         MyError::Spi
+/// #     }
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let mut frame = [Apa102Pixel::default(); 100];
+/// # let udp_payload = [0; 64];
+/// let packet = DdpPacket::parse(&udp_payload).ok_or(MyError::InvalidPacket)?;
+/// packet.apply(&mut frame);
+/// if packet.push() {
+///     apa102.write(frame.iter().copied())?;
+/// }
+/// # Ok::<(), MyError>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct DdpPacket<'a> {
+    flags: u8,
+    /// Offset of the data in the frame, in bytes
+    pub offset: usize,
+    data: &'a [u8],
+}
+
+impl<'a> DdpPacket<'a> {
+    /// Parse a whole UDP payload, `None` if it isn't a valid DDP version 1
+    /// data packet
+    ///
+    /// The data is always treated as 8 bit RGB.
+    pub fn parse(packet: &'a [u8]) -> Option<DdpPacket<'a>> {
+        let (header, rest) = packet.split_first_chunk::<10>()?;
+        let flags = header[0];
+        if flags & VERSION_MASK != VERSION_1 {
+            return None;
+        }
+        let offset = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        // The timecode is of no use for driving leds
+        let data = if flags & TIMECODE != 0 {
+            rest.get(4..)?
+        } else {
+            rest
+        };
+        Some(Self {
+            flags,
+            offset,
+            data: data.get(..len)?,
+        })
+    }
+
+    /// Whether the frame is complete and should be shown
+    pub fn push(&self) -> bool {
+        self.flags & PUSH != 0
+    }
+
+    /// Iterate over the pixels of this packet
+    ///
+    /// For packets holding a whole frame, as is usual for short strips, this
+    /// can be passed to `write` directly.
+    pub fn pixels(&self) -> impl Iterator<Item = Apa102Pixel> + 'a {
+        self.data
+            .chunks_exact(3)
+            .map(|rgb| RGB8::new(rgb[0], rgb[1], rgb[2]).into())
+    }
+
+    /// Copy the data of this packet into `frame` at its offset
+    ///
+    /// The pixels written to are set to full brightness, like the ones from
+    /// `pixels`. Data beyond the end of `frame` is dropped.
+    pub fn apply(&self, frame: &mut [Apa102Pixel]) {
+        for (index, &value) in (self.offset..).zip(self.data) {
+            let Some(pixel) = frame.get_mut(index / 3) else {
+                break;
+            };
+            pixel.brightness = 255;
+            match index % 3 {
+                0 => pixel.color.r = value,
+                1 => pixel.color.g = value,
+                _ => pixel.color.b = value,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::colors;
+
+    fn packet(flags: u8, offset: u32, data: &[u8]) -> Vec<u8> {
+        let mut packet = [flags, 0, 0x01, 0x01].to_vec();
+        packet.extend_from_slice(&offset.to_be_bytes());
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(data);
+        packet
+    }
+
+    #[test]
+    fn parses_packet() {
+        let bytes = packet(VERSION_1 | PUSH, 6, &[1, 2, 3, 4, 5, 6]);
+        let packet = DdpPacket::parse(&bytes).unwrap();
+        assert!(packet.push());
+        assert_eq!(packet.offset, 6);
+        assert_eq!(
+            colors(packet.pixels()),
+            [RGB8::new(1, 2, 3), RGB8::new(4, 5, 6)]
+        );
+    }
+
+    #[test]
+    fn skips_timecode() {
+        let mut bytes = packet(VERSION_1 | TIMECODE, 0, &[1, 2, 3]);
+        bytes.splice(10..10, [9; 4]);
+        let packet = DdpPacket::parse(&bytes).unwrap();
+        assert!(!packet.push());
+        assert_eq!(colors(packet.pixels()), [RGB8::new(1, 2, 3)]);
+    }
+
+    #[test]
+    fn rejects_malformed_packets() {
+        let valid = packet(VERSION_1, 0, &[1, 2, 3]);
+        assert!(DdpPacket::parse(&valid).is_some());
+
+        let mut wrong_version = valid.clone();
+        wrong_version[0] = 0x80;
+        assert!(DdpPacket::parse(&wrong_version).is_none());
+        // Shorter than the header, and than the announced length
+        assert!(DdpPacket::parse(&valid[..9]).is_none());
+        assert!(DdpPacket::parse(&valid[..valid.len() - 1]).is_none());
+        let mut missing_timecode = valid.clone();
+        missing_timecode[0] |= TIMECODE;
+        assert!(DdpPacket::parse(&missing_timecode).is_none());
+    }
+
+    #[test]
+    fn ignores_bytes_past_length() {
+        let mut bytes = packet(VERSION_1, 0, &[1, 2, 3]);
+        bytes.extend_from_slice(&[4, 5, 6]);
+        let packet = DdpPacket::parse(&bytes).unwrap();
+        assert_eq!(colors(packet.pixels()), [RGB8::new(1, 2, 3)]);
+    }
+
+    #[test]
+    fn assembles_frame_from_packets() {
+        let mut frame = [Apa102Pixel::default(); 3];
+        // Split in the middle of a pixel, the last one running past the frame
+        let first = packet(VERSION_1, 0, &[1, 2, 3, 4]);
+        let last = packet(VERSION_1 | PUSH, 4, &[5, 6, 7, 8, 9, 10, 11, 12]);
+        DdpPacket::parse(&first).unwrap().apply(&mut frame);
+        let last = DdpPacket::parse(&last).unwrap();
+        last.apply(&mut frame);
+        assert!(last.push());
+        let colors: Vec<_> = frame.iter().map(|pixel| pixel.color).collect();
+        assert_eq!(
+            colors,
+            [RGB8::new(1, 2, 3), RGB8::new(4, 5, 6), RGB8::new(7, 8, 9)]
+        );
+        assert!(frame.iter().all(|pixel| pixel.brightness != 0));
+    }
+}
@@ -0,0 +1,329 @@
+use core::iter::repeat_n;
+
+use embedded_hal::spi::SpiDevice;
+
+use smart_leds_trait::SmartLedsWrite;
+
+use crate::{Apa102Encoder, Apa102Pixel, ChipVariant, Error, Frame, PixelOrder};
+
+/// A controller for a series of APA102 LEDs behind an `SpiDevice`
+///
+/// An `SpiDevice` transaction can't be kept open while pixels are produced, so
+/// each frame is encoded into `buffer` first and then sent in a single
+/// transaction. This keeps chip select handling from splitting the protocol
+/// mid-frame. The buffer needs to hold `Apa102Encoder::frame_len` bytes.
+///
+/// This also makes it usable on a shared bus, e.g. with the `ExclusiveDevice`,
+/// `CriticalSectionDevice` or `RefCellDevice` wrappers from `embedded-hal-bus`.
+/// The bus is only acquired once per frame:
+///
+/// ```no_run
+/// # use core::cell::RefCell;
+/// # use core::convert::Infallible;
+/// # use apa102_spi::Apa102Device;
+/// # use embedded_hal::{delay::DelayNs, digital, digital::OutputPin, spi, spi::SpiBus};
+/// # use embedded_hal_bus::spi::RefCellDevice;
+/// # use smart_leds_trait::{SmartLedsWrite, RGB8};
+/// # struct Spi;
+/// # impl spi::ErrorType for Spi {
+/// #     type Error = Infallible;
+/// # }
+/// # impl SpiBus for Spi {
+/// #     fn read(&mut self, _: &mut [u8]) -> Result<(), Infallible> {
+/// #         Ok(())
+/// #     }
+/// #     fn write(&mut self, _: &[u8]) -> Result<(), Infallible> {
+/// #         Ok(())
+/// #     }
+/// #     fn transfer(&mut self, _: &mut [u8], _: &[u8]) -> Result<(), Infallible> {
+/// #         Ok(())
+/// #     }
+/// #     fn transfer_in_place(&mut self, _: &mut [u8]) -> Result<(), Infallible> {
+/// #         Ok(())
+/// #     }
+/// #     fn flush(&mut self) -> Result<(), Infallible> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Pin;
+/// # impl digital::ErrorType for Pin {
+/// #     type Error = Infallible;
+/// # }
+/// # impl OutputPin for Pin {
+/// #     fn set_low(&mut self) -> Result<(), Infallible> {
+/// #         Ok(())
+/// #     }
+/// #     fn set_high(&mut self) -> Result<(), Infallible> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Delay;
+/// # impl DelayNs for Delay {
+/// #     fn delay_ns(&mut self, _: u32) {}
+/// # }
+/// # struct Display<SPI>(SPI);
+/// # impl<SPI> Display<SPI> {
+/// #     fn new(spi: SPI) -> Self {
+/// #         Display(spi)
+/// #     }
+/// # }
+/// # #[derive(Debug)]
+/// # struct MyError;
+/// # impl From<Infallible> for MyError {
+/// #     fn from(_: Infallible) -> Self {
+/// #         MyError
+/// #     }
+/// # }
+/// # impl<E> From<apa102_spi::Error<E>> for MyError {
+/// #     fn from(_: apa102_spi::Error<E>) -> Self {
+/// #         MyError
+/// #     }
+/// # }
+/// # const NUM_LEDS: usize = 60;
+/// # let (spi, display_cs, unused_cs, delay) = (Spi, Pin, Pin, Delay);
+/// # let colors = [RGB8::default(); NUM_LEDS];
+/// let bus = RefCell::new(spi);
+/// let mut display = Display::new(RefCellDevice::new(&bus, display_cs, delay)?);
+/// let mut buffer = [0; 4 + 4 * NUM_LEDS + 4];
+/// // APA102 leds have no chip select, `unused_cs` can be any dummy `OutputPin`
+/// let mut leds = Apa102Device::new(RefCellDevice::new_no_delay(&bus, unused_cs)?, &mut buffer);
+/// leds.write(colors.iter().cloned())?;
+/// # Ok::<(), MyError>(())
+/// ```
+pub struct Apa102Device<'a, SPI> {
+    spi: SPI,
+    buffer: &'a mut [u8],
+    encoder: Apa102Encoder,
+    index_map: Option<&'static [usize]>,
+}
+
+impl<'a, SPI> Apa102Device<'a, SPI>
+where
+    SPI: SpiDevice,
+{
+    /// new constructs a controller using the same defaults as `Apa102::new`
+    pub fn new(spi: SPI, buffer: &'a mut [u8]) -> Apa102Device<'a, SPI> {
+        Self {
+            spi,
+            buffer,
+            encoder: Apa102Encoder::new(),
+            index_map: None,
+        }
+    }
+
+    /// The options match `Apa102::new_with_options`
+    pub fn new_with_options(
+        spi: SPI,
+        buffer: &'a mut [u8],
+        end_frame_length: u8,
+        invert_end_frame: bool,
+        pixel_order: PixelOrder,
+    ) -> Apa102Device<'a, SPI> {
+        Self {
+            spi,
+            buffer,
+            encoder: Apa102Encoder::new_with_options(
+                end_frame_length,
+                invert_end_frame,
+                pixel_order,
+            ),
+            index_map: None,
+        }
+    }
+
+    /// new_with_chip_variant constructs a controller with the defaults of
+    /// new(), adapted to the quirks of `chip_variant`
+    pub fn new_with_chip_variant(
+        spi: SPI,
+        buffer: &'a mut [u8],
+        chip_variant: ChipVariant,
+    ) -> Apa102Device<'a, SPI> {
+        Self {
+            spi,
+            buffer,
+            encoder: Apa102Encoder::new_with_chip_variant(chip_variant),
+            index_map: None,
+        }
+    }
+
+    /// with_encoder constructs a controller encoding pixels with `encoder`,
+    /// e.g. one made by `Apa102Builder::build_encoder`
+    pub fn with_encoder(
+        spi: SPI,
+        buffer: &'a mut [u8],
+        encoder: Apa102Encoder,
+    ) -> Apa102Device<'a, SPI> {
+        Self {
+            spi,
+            buffer,
+            encoder,
+            index_map: None,
+        }
+    }
+
+    /// The encoder holding the settings of the controller
+    pub fn encoder(&self) -> &Apa102Encoder {
+        &self.encoder
+    }
+
+    /// Borrow the encoder to change the settings of the controller, e.g.
+    /// `leds.encoder_mut().set_brightness(64)`
+    pub fn encoder_mut(&mut self) -> &mut Apa102Encoder {
+        &mut self.encoder
+    }
+
+    /// Set where each pixel is placed on the strip, e.g. to skip a dead led
+    /// or to follow out of order wiring
+    ///
+    /// The pixel at each index is sent to the led at the position in
+    /// `index_map` at the same index, so the pixels can keep their logical
+    /// order. Pixels past the end of `index_map` are dropped. Leds no pixel is
+    /// placed on are turned off. The calibration of
+    /// `Apa102Encoder::set_calibration` follows the position on the strip.
+    ///
+    /// Pixels are reordered in the buffer, so unlike the other settings this
+    /// isn't available on `Apa102`, which sends them as they come.
+    pub fn set_index_map(&mut self, index_map: Option<&'static [usize]>) {
+        self.index_map = index_map;
+    }
+
+    /// Write an already encoded frame in a single transaction
+    pub fn write_raw_frame(&mut self, frame: &[u8]) -> Result<(), SPI::Error> {
+        self.spi.write(frame)
+    }
+
+    /// Write pixels from a flat slice of RGB triplets, e.g. received from a
+    /// host
+    ///
+    /// A new pixel starts every `stride` bytes, with its first three bytes
+    /// being red, green and blue. All pixels get the same `brightness`.
+    /// Incomplete pixels at the end are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride` is smaller than 3.
+    pub fn write_rgb8_bytes(
+        &mut self,
+        bytes: &[u8],
+        stride: usize,
+        brightness: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        assert!(stride >= 3, "stride must be at least 3");
+        let pixels = bytes
+            .chunks(stride)
+            .filter(|pixel| pixel.len() >= 3)
+            .map(|pixel| Apa102Pixel::new(pixel[0], pixel[1], pixel[2], brightness));
+        self.write(pixels)
+    }
+
+    /// Write all the items of an iterator, using `brightness` instead of the
+    /// global brightness for this frame only
+    pub fn write_with_brightness<T, I>(
+        &mut self,
+        iterator: T,
+        brightness: u8,
+    ) -> Result<(), Error<SPI::Error>>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        self.encoder.next_frame();
+        let len = self
+            .encoder
+            .encode_with(iterator, self.buffer, Some(brightness), self.index_map)
+            .ok_or(Error::BufferTooSmall)?;
+        self.spi.write(&self.buffer[..len]).map_err(Error::Spi)
+    }
+
+    /// Write all the items of an iterator, checking they are as many as the
+    /// strip has leds
+    ///
+    /// Nothing is written if the number of items doesn't match the one set
+    /// with `Apa102Encoder::set_num_leds`. Without it any number is accepted.
+    pub fn write_exact<T, I>(&mut self, iterator: T) -> Result<(), Error<SPI::Error>>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        self.encoder.next_frame();
+        let mut actual = 0;
+        let counted = iterator.into_iter().inspect(|_| actual += 1);
+        let len = self
+            .encoder
+            .encode_with(counted, self.buffer, None, self.index_map)
+            .ok_or(Error::BufferTooSmall)?;
+        match self.encoder.num_leds() {
+            Some(expected) if actual != expected => Err(Error::WrongLength { expected, actual }),
+            _ => self.spi.write(&self.buffer[..len]).map_err(Error::Spi),
+        }
+    }
+
+    /// Set every led of the strip to `pixel`, e.g. an `RGB8` color
+    ///
+    /// Writes as many copies as set with `Apa102Encoder::set_num_leds`, and
+    /// fails with `Error::NumLedsUnset` without it.
+    pub fn fill(&mut self, pixel: impl Into<Apa102Pixel>) -> Result<(), Error<SPI::Error>> {
+        let num_leds = self.encoder.num_leds().ok_or(Error::NumLedsUnset)?;
+        self.write(repeat_n(pixel.into(), num_leds))
+    }
+
+    /// Switch every led of the strip off
+    ///
+    /// Writes black pixels followed by the end frame, see `fill`.
+    pub fn clear(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.fill(Apa102Pixel::default())
+    }
+
+    /// The number of leds of the strip, see `Apa102Encoder::num_leds`
+    pub fn num_leds(&self) -> Option<usize> {
+        self.encoder.num_leds()
+    }
+
+    /// The order the colors are sent in, see `Apa102Encoder::pixel_order`
+    pub fn pixel_order(&self) -> PixelOrder {
+        self.encoder.pixel_order()
+    }
+
+    /// Length of the end frame following `num_leds` pixels, see
+    /// `Apa102Encoder::end_frame_len`
+    pub fn end_frame_len(&self, num_leds: usize) -> usize {
+        self.encoder.end_frame_len(num_leds)
+    }
+
+    /// Wrap `pixels` in a `Frame`, `None` if their number doesn't match
+    /// the one set with `Apa102Encoder::set_num_leds`
+    pub fn frame<'b>(&self, pixels: &'b mut [Apa102Pixel]) -> Option<Frame<'b>> {
+        Frame::new(pixels, self.encoder.num_leds()?)
+    }
+
+    /// Borrow the SPI, e.g. to change its frequency, without consuming self
+    pub fn bus_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// Free the owned resources consuming self
+    pub fn free(self) -> (SPI, &'a mut [u8]) {
+        (self.spi, self.buffer)
+    }
+}
+
+impl<SPI> SmartLedsWrite for Apa102Device<'_, SPI>
+where
+    SPI: SpiDevice,
+{
+    type Color = Apa102Pixel;
+    type Error = Error<SPI::Error>;
+    /// Write all the items of an iterator to an apa102 strip
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.encoder.next_frame();
+        let len = self
+            .encoder
+            .encode_with(iterator, self.buffer, None, self.index_map)
+            .ok_or(Error::BufferTooSmall)?;
+        self.spi.write(&self.buffer[..len]).map_err(Error::Spi)
+    }
+}
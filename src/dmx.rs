@@ -0,0 +1,174 @@
+use crate::Apa102Pixel;
+
+/// Where a `DmxMapping` takes the brightness of the pixels from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmxBrightness {
+    /// Every pixel is at full brightness
+    Full,
+    /// Every pixel has its brightness in the channel following its color
+    PerPixel,
+    /// A single dimmer channel at the start channel sets the brightness of
+    /// all pixels, which follow it
+    Master,
+}
+
+/// Maps the channels of a DMX universe, e.g. received via sACN (E1.31) or
+/// Art-Net, to pixels
+///
+/// Every pixel starts with its red, green and blue channels. Channels of a
+/// pixel beyond these and the brightness channel, e.g. the white channel of
+/// RGBW fixtures, are ignored.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, ClockedLedBus, DmxBrightness, DmxMapping};
+/// # use smart_leds_trait::SmartLedsWrite;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # const NUM_LEDS: usize = 60;
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let universe = [0; 512];
+/// // 4 channels per pixel starting at channel 10, the 4th one for brightness
+/// let mapping = DmxMapping::new_with_options(10, 4, DmxBrightness::PerPixel);
+/// apa102.write(mapping.pixels(&universe).take(NUM_LEDS))?;
+/// # Ok::<(), ()>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct DmxMapping {
+    start_channel: u16,
+    channels_per_pixel: u8,
+    brightness: DmxBrightness,
+}
+
+impl DmxMapping {
+    /// new constructs a mapping for RGB pixels at full brightness, starting
+    /// at `start_channel`. The other options can be customized using
+    /// new_with_options()
+    pub fn new(start_channel: u16) -> DmxMapping {
+        Self::new_with_options(start_channel, 3, DmxBrightness::Full)
+    }
+
+    /// `start_channel` counts from 1, like DMX channels are usually numbered.
+    /// `channels_per_pixel` is raised to the channels needed by `brightness`
+    /// if it is lower.
+    pub fn new_with_options(
+        start_channel: u16,
+        channels_per_pixel: u8,
+        brightness: DmxBrightness,
+    ) -> DmxMapping {
+        let needed = match brightness {
+            DmxBrightness::PerPixel => 4,
+            _ => 3,
+        };
+        Self {
+            start_channel: start_channel.max(1),
+            channels_per_pixel: channels_per_pixel.max(needed),
+            brightness,
+        }
+    }
+
+    /// Iterate over the pixels mapped from `universe`, the slots of a DMX
+    /// universe without the start code
+    ///
+    /// Pixels are mapped until the end of `universe`, a pixel only partly
+    /// in it is dropped.
+    pub fn pixels<'a>(&self, universe: &'a [u8]) -> impl Iterator<Item = Apa102Pixel> + 'a {
+        let mut start = self.start_channel as usize - 1;
+        let master = match self.brightness {
+            DmxBrightness::Master => {
+                start += 1;
+                universe.get(start - 1).copied()
+            }
+            _ => None,
+        };
+        let per_pixel = self.brightness == DmxBrightness::PerPixel;
+        universe
+            .get(start..)
+            .unwrap_or_default()
+            .chunks_exact(self.channels_per_pixel as usize)
+            .map(move |channels| {
+                let brightness = match (master, per_pixel) {
+                    (Some(master), _) => master,
+                    (None, true) => channels[3],
+                    (None, false) => 255,
+                };
+                Apa102Pixel::new(channels[0], channels[1], channels[2], brightness)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+
+    fn pixels(mapping: DmxMapping, universe: &[u8]) -> Vec<Apa102Pixel> {
+        mapping.pixels(universe).collect()
+    }
+
+    #[test]
+    fn maps_rgb_at_full_brightness() {
+        let universe = [9, 1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(
+            pixels(DmxMapping::new(2), &universe),
+            [
+                Apa102Pixel::new(1, 2, 3, 255),
+                Apa102Pixel::new(4, 5, 6, 255)
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_per_pixel_brightness() {
+        // Raised to the 4 channels needed
+        let universe = [1, 2, 3, 10, 4, 5, 6, 20];
+        let mapping = DmxMapping::new_with_options(1, 3, DmxBrightness::PerPixel);
+        assert_eq!(
+            pixels(mapping, &universe),
+            [Apa102Pixel::new(1, 2, 3, 10), Apa102Pixel::new(4, 5, 6, 20)]
+        );
+        // A fifth channel, e.g. white, is ignored
+        let universe = [1, 2, 3, 10, 99, 4, 5, 6, 20, 99];
+        let mapping = DmxMapping::new_with_options(1, 5, DmxBrightness::PerPixel);
+        assert_eq!(
+            pixels(mapping, &universe),
+            [Apa102Pixel::new(1, 2, 3, 10), Apa102Pixel::new(4, 5, 6, 20)]
+        );
+    }
+
+    #[test]
+    fn maps_master_dimmer() {
+        let universe = [0, 50, 1, 2, 3, 4, 5, 6];
+        let mapping = DmxMapping::new_with_options(2, 3, DmxBrightness::Master);
+        assert_eq!(
+            pixels(mapping, &universe),
+            [Apa102Pixel::new(1, 2, 3, 50), Apa102Pixel::new(4, 5, 6, 50)]
+        );
+    }
+
+    #[test]
+    fn drops_pixels_past_the_universe() {
+        assert_eq!(
+            pixels(DmxMapping::new(1), &[1, 2, 3, 4, 5]),
+            [Apa102Pixel::new(1, 2, 3, 255)]
+        );
+        assert!(pixels(DmxMapping::new(10), &[1, 2, 3]).is_empty());
+        let mapping = DmxMapping::new_with_options(4, 3, DmxBrightness::Master);
+        assert!(pixels(mapping, &[1, 2, 3]).is_empty());
+    }
+
+    #[test]
+    fn counts_channels_from_one() {
+        assert_eq!(
+            pixels(DmxMapping::new(0), &[1, 2, 3]),
+            pixels(DmxMapping::new(1), &[1, 2, 3])
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Easing curves, ported from FastLED
+//!
+//! Each maps the progress of a transition, going from 0 to the maximum of
+//! its type, to the share of it to apply, e.g. as `amount` of
+//! `Apa102Pixel::lerp`:
+//!
+//! ```no_run
+//! # use apa102_spi::easing::ease8_in_out_cubic;
+//! # use apa102_spi::Apa102Pixel;
+//! # let (from, to) = (Apa102Pixel::default(), Apa102Pixel::new(255, 0, 0, 255));
+//! # let progress = 128;
+//! let pixel = from.lerp(to, ease8_in_out_cubic(progress));
+//! ```
+
+pub use crate::math::{ease16_in_out_quad, ease8_in_out_quad};
+
+/// Cubic ease in and out over the whole range of `u8`
+pub fn ease8_in_out_cubic(i: u8) -> u8 {
+    // 3x^2 - 2x^3, computed exactly as the scaled products used by FastLED
+    // aren't monotonic
+    let x = i as u32;
+    (x * x * (3 * 255 - 2 * x) / (255 * 255)) as u8
+}
+
+/// Cubic ease in and out over the whole range of `u16`
+pub fn ease16_in_out_cubic(i: u16) -> u16 {
+    let x = i as u64;
+    (x * x * (3 * 65535 - 2 * x) / (65535 * 65535)) as u16
+}
@@ -0,0 +1,912 @@
+use core::iter::repeat;
+use smart_leds_trait::RGB8;
+
+use crate::chunked::Chunked;
+use crate::conversion::{five_bit, five_bit_dithered, pseudo13, scale_color, scale_color_video};
+use crate::math::{scale8, scale8_video};
+use crate::{
+    end_frame_byte, Apa102Pixel, BrightnessCurve, BrightnessStrategy, ChipVariant, ClockedLedBus,
+    ColorTemperature, Derating, Gamma, PixelOrder, Segment, TemperatureCompensation, U5,
+    UNCORRECTED,
+};
+
+/// Encodes colors into raw APA102 protocol bytes without touching SPI
+///
+/// Useful for handing a complete frame to a DMA engine directly.
+#[derive(Clone)]
+pub struct Apa102Encoder {
+    start_frame_length: u8,
+    end_frame_length: u8,
+    invert_end_frame: bool,
+    extra_end_frame_length: u8,
+    end_frame_multiplier: u8,
+    pixel_order: PixelOrder,
+    brightness: u8,
+    // `None` until set, following the chip variant
+    brightness_strategy: Option<BrightnessStrategy>,
+    brightness_curve: BrightnessCurve,
+    brightness_ramp: Option<u8>,
+    ramped_brightness: u8,
+    dithering: bool,
+    video_scaling: bool,
+    derating: Option<Derating>,
+    temperature_compensation: Option<TemperatureCompensation>,
+    calibration: Option<&'static [RGB8]>,
+    segments: &'static [Segment],
+    led_temperature_c: Option<i16>,
+    compensation: RGB8,
+    supply_voltage_mv: Option<u16>,
+    derating_scale: u8,
+    frame: u8,
+    global_current: Option<U5>,
+    five_bit_floor: u8,
+    chip_variant: ChipVariant,
+    num_leds: Option<usize>,
+    fit_to_num_leds: bool,
+    color_conversion: Option<fn(RGB8) -> Apa102Pixel>,
+    gamma: Option<Gamma>,
+    color_correction: RGB8,
+    color_temperature: ColorTemperature,
+}
+
+impl Apa102Encoder {
+    /// new constructs an encoder using the same defaults as `Apa102::new`
+    pub fn new() -> Apa102Encoder {
+        Self::new_with_options(4, true, PixelOrder::BGR)
+    }
+
+    /// The options match `Apa102::new_with_options`
+    pub fn new_with_options(
+        end_frame_length: u8,
+        invert_end_frame: bool,
+        pixel_order: PixelOrder,
+    ) -> Apa102Encoder {
+        Self {
+            start_frame_length: 4,
+            end_frame_length,
+            invert_end_frame,
+            extra_end_frame_length: 0,
+            end_frame_multiplier: 1,
+            pixel_order,
+            brightness: 255,
+            brightness_strategy: None,
+            brightness_curve: BrightnessCurve::Linear,
+            brightness_ramp: None,
+            ramped_brightness: 0,
+            dithering: false,
+            video_scaling: false,
+            derating: None,
+            temperature_compensation: None,
+            calibration: None,
+            segments: &[],
+            led_temperature_c: None,
+            compensation: UNCORRECTED,
+            supply_voltage_mv: None,
+            derating_scale: 255,
+            frame: 0,
+            global_current: None,
+            five_bit_floor: 0,
+            chip_variant: ChipVariant::Apa102,
+            num_leds: None,
+            fit_to_num_leds: false,
+            color_conversion: None,
+            gamma: None,
+            color_correction: RGB8::new(255, 255, 255),
+            color_temperature: ColorTemperature::UNCORRECTED,
+        }
+    }
+
+    /// new_with_chip_variant constructs an encoder with the defaults of new(),
+    /// adapted to the quirks of `chip_variant`
+    pub fn new_with_chip_variant(chip_variant: ChipVariant) -> Apa102Encoder {
+        let mut encoder = Self::new();
+        encoder.set_chip_variant(chip_variant);
+        encoder
+    }
+
+    /// Set the kind of leds driven
+    ///
+    /// This selects whether a reset frame is sent after the pixel data, and
+    /// the brightness strategy preferred for these leds unless one was set
+    /// with `set_brightness_strategy`.
+    pub fn set_chip_variant(&mut self, chip_variant: ChipVariant) {
+        self.chip_variant = chip_variant;
+    }
+
+    /// Set the number of leds of the strip
+    ///
+    /// The end frame is then sized for the whole strip even when fewer leds
+    /// are written. With `None`, the default, it is sized for the leds
+    /// written.
+    pub fn set_num_leds(&mut self, num_leds: Option<usize>) {
+        self.num_leds = num_leds;
+    }
+
+    /// The number of leds of the strip, see `set_num_leds`
+    pub fn num_leds(&self) -> Option<usize> {
+        self.num_leds
+    }
+
+    /// Pad frames shorter than the strip with black pixels and cut longer ones
+    /// to its length
+    ///
+    /// Lets frames of any length, e.g. received from the network, be written
+    /// without counting pixels. Only has an effect after `set_num_leds`.
+    pub fn set_fit_to_num_leds(&mut self, fit_to_num_leds: bool) {
+        self.fit_to_num_leds = fit_to_num_leds;
+    }
+
+    /// Set a function every color is passed through before being encoded
+    ///
+    /// Useful for applying custom gamma, clamping or white balance to every
+    /// frame. The function returns a whole pixel, so it can set the
+    /// brightness as well, which is combined with the brightness of the pixel
+    /// written. The global brightness is applied afterwards.
+    pub fn set_color_conversion(&mut self, color_conversion: Option<fn(RGB8) -> Apa102Pixel>) {
+        self.color_conversion = color_conversion;
+    }
+
+    /// Set gamma curves every color is mapped through, after the color
+    /// conversion and before the color correction
+    ///
+    /// With `None`, the default, colors are sent as they are.
+    pub fn set_gamma(&mut self, gamma: Option<Gamma>) {
+        self.gamma = gamma;
+    }
+
+    /// Set a per channel correction applied to every color
+    ///
+    /// Each channel is scaled by its value in `color_correction`, 255 leaving
+    /// it unchanged. Useful for calibrating the white point of a strip.
+    pub fn set_color_correction(&mut self, color_correction: RGB8) {
+        self.color_correction = color_correction;
+    }
+
+    /// Set the color temperature of the light the leds should match
+    ///
+    /// Applied together with the color correction, e.g.
+    /// `ColorTemperature::TUNGSTEN_100W` makes white leds look like an
+    /// incandescent bulb.
+    pub fn set_color_temperature(&mut self, color_temperature: ColorTemperature) {
+        self.color_temperature = color_temperature;
+    }
+
+    /// Set the order the color channels are transmitted in
+    pub fn set_pixel_order(&mut self, pixel_order: PixelOrder) {
+        self.pixel_order = pixel_order;
+    }
+
+    /// The order the colors are sent in, see `set_pixel_order`
+    pub fn pixel_order(&self) -> PixelOrder {
+        self.pixel_order
+    }
+
+    /// Set the number of zero bytes sent before the pixel data, 4 by default
+    ///
+    /// Some long strips and clones need a longer start frame to not corrupt
+    /// the first pixel.
+    pub fn set_start_frame_length(&mut self, start_frame_length: u8) {
+        self.start_frame_length = start_frame_length;
+    }
+
+    /// Set the minimum number of bytes sent after the pixel data, see
+    /// `Apa102::new_with_options`
+    pub fn set_end_frame_length(&mut self, end_frame_length: u8) {
+        self.end_frame_length = end_frame_length;
+    }
+
+    /// Send the end frame as zeroes instead of 0xFF, see
+    /// `Apa102::new_with_options`
+    pub fn set_invert_end_frame(&mut self, invert_end_frame: bool) {
+        self.invert_end_frame = invert_end_frame;
+    }
+
+    /// Add `extra_end_frame_length` bytes to the computed end frame length
+    ///
+    /// Useful when something in the chain, like a signal repeater, needs more
+    /// clock cycles than the leds alone.
+    pub fn set_extra_end_frame_length(&mut self, extra_end_frame_length: u8) {
+        self.extra_end_frame_length = extra_end_frame_length;
+    }
+
+    /// Multiply the end frame length derived from the number of leds written
+    ///
+    /// The end frame needs at least half a bit per led. Very long strips can
+    /// need a safety margin on top of that to latch without glitches.
+    pub fn set_end_frame_multiplier(&mut self, end_frame_multiplier: u8) {
+        self.end_frame_multiplier = end_frame_multiplier;
+    }
+
+    /// Set the global brightness applied to every pixel, 255 being the
+    /// default full brightness
+    pub fn set_brightness(&mut self, brightness: u8) {
+        // With a brightness ramp, raising it ramps up from the current one
+        self.ramped_brightness = self.ramped_brightness.min(self.brightness).min(brightness);
+        self.brightness = brightness;
+    }
+
+    /// Set how the global brightness is applied, see `BrightnessStrategy`
+    ///
+    /// Takes precedence over the strategy preferred by the chip variant, no
+    /// matter which is set first.
+    pub fn set_brightness_strategy(&mut self, brightness_strategy: BrightnessStrategy) {
+        self.brightness_strategy = Some(brightness_strategy);
+    }
+
+    /// The strategy the global brightness is applied with, the one set with
+    /// `set_brightness_strategy` or else the one preferred by the chip
+    /// variant
+    pub fn brightness_strategy(&self) -> BrightnessStrategy {
+        self.brightness_strategy
+            .unwrap_or(self.chip_variant.brightness_strategy())
+    }
+
+    /// Set how the brightness is mapped to light output, see
+    /// `BrightnessCurve`
+    ///
+    /// The curve applies to the global brightness combined with the
+    /// brightness of each pixel, before the `BrightnessStrategy`.
+    pub fn set_brightness_curve(&mut self, brightness_curve: BrightnessCurve) {
+        self.brightness_curve = brightness_curve;
+    }
+
+    /// Limit how much the global brightness may increase per frame
+    ///
+    /// Turning a long strip from off to full white in a single frame can trip
+    /// the overcurrent protection of its power supply. With a ramp, starting
+    /// from off, the brightness rises by at most `brightness_ramp` every frame
+    /// until it reaches the one set. Lowering it takes effect at once. With
+    /// `None`, the default, it is never limited.
+    pub fn set_brightness_ramp(&mut self, brightness_ramp: Option<u8>) {
+        self.brightness_ramp = brightness_ramp;
+    }
+
+    /// Dither the 5 bit brightness field over consecutive frames
+    ///
+    /// With `BrightnessStrategy::HardwareFiveBit` only 31 brightness levels
+    /// are available, which makes slow fades visibly step. With dithering
+    /// pixels alternate between the two nearest levels to produce the ones in
+    /// between, see `conversion::five_bit_dithered`. Needs frames written at
+    /// a high rate to not flicker.
+    pub fn set_dithering(&mut self, dithering: bool) {
+        self.dithering = dithering;
+    }
+
+    /// Never scale a lit channel down to zero
+    ///
+    /// By default dim colors and low brightness values round some channels off
+    /// completely, making sparse animations blink while fading out. With video
+    /// scaling, like `scale8_video` in FastLED, a non-zero channel of a pixel
+    /// with a non-zero brightness keeps at least its lowest value.
+    pub fn set_video_scaling(&mut self, video_scaling: bool) {
+        self.video_scaling = video_scaling;
+    }
+
+    /// Set how the brightness is lowered as the supply voltage drops, see
+    /// `Derating`
+    ///
+    /// The measured voltage is passed with `set_supply_voltage_mv`. With
+    /// `None`, the default, the brightness doesn't depend on it.
+    pub fn set_derating(&mut self, derating: Option<Derating>) {
+        self.derating = derating;
+        self.update_derating_scale();
+    }
+
+    /// Set the last measured supply voltage, in mV, see `set_derating`
+    pub fn set_supply_voltage_mv(&mut self, supply_voltage_mv: u16) {
+        self.supply_voltage_mv = Some(supply_voltage_mv);
+        self.update_derating_scale();
+    }
+
+    fn update_derating_scale(&mut self) {
+        self.derating_scale = match (self.derating, self.supply_voltage_mv) {
+            (Some(derating), Some(supply_voltage_mv)) => derating.scale(supply_voltage_mv),
+            _ => 255,
+        };
+    }
+
+    /// Set how the color correction follows the temperature of the leds, see
+    /// `TemperatureCompensation`
+    ///
+    /// The measured temperature is passed with `set_led_temperature_c`. With
+    /// `None`, the default, the correction doesn't depend on it.
+    pub fn set_temperature_compensation(
+        &mut self,
+        temperature_compensation: Option<TemperatureCompensation>,
+    ) {
+        self.temperature_compensation = temperature_compensation;
+        self.update_compensation();
+    }
+
+    /// Set the last measured temperature of the leds, in °C, see
+    /// `set_temperature_compensation`
+    pub fn set_led_temperature_c(&mut self, led_temperature_c: i16) {
+        self.led_temperature_c = Some(led_temperature_c);
+        self.update_compensation();
+    }
+
+    fn update_compensation(&mut self) {
+        self.compensation = match (self.temperature_compensation, self.led_temperature_c) {
+            (Some(compensation), Some(led_temperature_c)) => {
+                compensation.correction_at(led_temperature_c)
+            }
+            _ => UNCORRECTED,
+        };
+    }
+
+    /// Set a correction per led, measured to even out differences between them
+    ///
+    /// The color of the pixel at each index is scaled by the entry at the same
+    /// index, like `set_color_correction` does for all of them. Pixels past the
+    /// end of `calibration` are left alone. With `None`, the default, no per
+    /// led correction is applied.
+    pub fn set_calibration(&mut self, calibration: Option<&'static [RGB8]>) {
+        self.calibration = calibration;
+    }
+
+    /// Set the segments the strip is split into, see `Segment`
+    ///
+    /// Pixels in a segment with its own pixel order or brightness strategy are
+    /// encoded with those, e.g. for a chain of different kinds of leds.
+    /// Segments are matched by the position on the strip.
+    pub fn set_segments(&mut self, segments: &'static [Segment]) {
+        self.segments = segments;
+    }
+
+    /// The segments the strip is split into, see `set_segments`
+    pub fn segments(&self) -> &'static [Segment] {
+        self.segments
+    }
+
+    /// Advance to the next frame of the dithering pattern and the brightness
+    /// ramp
+    ///
+    /// Done by the controllers on every write. Only needed when calling
+    /// `encode` directly with dithering or a brightness ramp enabled.
+    pub fn next_frame(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+        if let Some(step) = self.brightness_ramp {
+            self.ramped_brightness = self.ramped_brightness.saturating_add(step);
+        }
+    }
+
+    /// Whether the same pixel may be encoded differently in the next frame,
+    /// because of dithering or a brightness ramp that is still rising
+    pub(crate) fn varies_per_frame(&self) -> bool {
+        self.dithering || self.brightness_ramp.is_some() && self.ramped_brightness < self.brightness
+    }
+
+    /// Send the same 5 bit value in the brightness field of every pixel
+    ///
+    /// Meant for SK9822 leds, which use this field to control a constant
+    /// current instead of a pwm duty cycle. The global brightness is then
+    /// always applied by scaling the colors. `None` restores the default of
+    /// following the `BrightnessStrategy`.
+    pub fn set_global_current(&mut self, global_current: Option<U5>) {
+        self.global_current = global_current;
+    }
+
+    /// Set the lowest value sent in the 5 bit brightness field
+    ///
+    /// At low values the brightness pwm of APA102 leds flickers visibly,
+    /// especially on camera. Pixels that would go below `five_bit_floor` are
+    /// sent with it instead, their colors scaled down to dim them the rest of
+    /// the way. Values above 31 are treated as 31, 0 being the default of no
+    /// floor. Has no effect with `set_global_current`.
+    pub fn set_five_bit_floor(&mut self, five_bit_floor: u8) {
+        self.five_bit_floor = five_bit_floor;
+    }
+
+    /// Whether options are set that contradict each other, see
+    /// `Error::ConflictingOptions`
+    pub(crate) fn has_conflicting_options(&self) -> bool {
+        let fit_without_num_leds = self.fit_to_num_leds && self.num_leds.is_none();
+        let ignored_for_global_current = self.global_current.is_some()
+            && (self.brightness_strategy.is_some() || self.five_bit_floor > 0 || self.dithering);
+        let dithering_without_five_bit =
+            self.dithering && self.brightness_strategy() != BrightnessStrategy::HardwareFiveBit;
+        let segments_past_end = self.num_leds.is_some_and(|num_leds| {
+            self.segments
+                .iter()
+                .any(|segment| segment.range().end > num_leds)
+        });
+        fit_without_num_leds
+            || ignored_for_global_current
+            || dithering_without_five_bit
+            || segments_past_end
+    }
+
+    /// Number of bytes needed to encode a frame for `num_leds` leds
+    pub fn frame_len(&self, num_leds: usize) -> usize {
+        self.start_frame_length as usize
+            + 4 * num_leds
+            + self.chip_variant.reset_frame_length()
+            + self.end_frame_len(num_leds)
+    }
+
+    /// Encode the start frame, all the items of an iterator and the end frame
+    /// into `buffer`
+    ///
+    /// Returns the number of bytes written, or `None` if `buffer` is too small
+    /// to hold the complete frame.
+    pub fn encode<T, I>(&self, iterator: T, buffer: &mut [u8]) -> Option<usize>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        self.encode_with(iterator, buffer, None, None)
+    }
+
+    /// Encode a frame like `encode`, using `brightness` instead of the global
+    /// brightness if given, and placing the pixels on the strip following
+    /// `index_map`, see `Apa102Device::set_index_map`
+    pub(crate) fn encode_with<T, I>(
+        &self,
+        iterator: T,
+        buffer: &mut [u8],
+        brightness: Option<u8>,
+        index_map: Option<&[usize]>,
+    ) -> Option<usize>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        let start = self.start_frame_length as usize;
+        buffer.get_mut(..start)?.fill(0x00);
+        // Includes the leds skipped over by the index map
+        let mut num_leds = 0;
+        for (index, pixel) in self.fit(iterator).enumerate() {
+            let Some(position) = position(index_map, index) else {
+                continue;
+            };
+            if position >= num_leds {
+                buffer
+                    .get_mut(start + 4 * num_leds..start + 4 * position)?
+                    .chunks_mut(4)
+                    .for_each(|pixel| pixel.copy_from_slice(&UNMAPPED_FRAME));
+                num_leds = position + 1;
+            }
+            let pixel = self.encode_pixel_with(pixel, Some(position), brightness);
+            let offset = start + 4 * position;
+            buffer.get_mut(offset..offset + 4)?.copy_from_slice(&pixel);
+        }
+        let len = start + 4 * num_leds;
+        let reset_end = len + self.chip_variant.reset_frame_length();
+        buffer.get_mut(len..reset_end)?.fill(0x00);
+        let end = reset_end + self.end_frame_len(num_leds);
+        buffer
+            .get_mut(reset_end..end)?
+            .fill(end_frame_byte(self.invert_end_frame));
+        Some(end)
+    }
+
+    /// Encode a complete frame and write it to `bus` in chunks, using
+    /// `brightness` instead of the global brightness if given
+    pub(crate) fn write_frame<B, T, I>(
+        &self,
+        bus: &mut B,
+        iterator: T,
+        brightness: Option<u8>,
+    ) -> Result<(), B::Error>
+    where
+        B: ClockedLedBus,
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        let mut chunked = Chunked::new(bus);
+        chunked.fill(0x00, self.start_frame_length as usize)?;
+        let mut num_leds = 0;
+        for pixel in self.fit(iterator) {
+            chunked.push(&self.encode_pixel_with(pixel, Some(num_leds), brightness))?;
+            num_leds += 1;
+        }
+        chunked.fill(0x00, self.chip_variant.reset_frame_length())?;
+        chunked.fill(
+            end_frame_byte(self.invert_end_frame),
+            self.end_frame_len(num_leds),
+        )?;
+        chunked.finish()
+    }
+
+    /// Write a frame of already encoded pixel frames to `bus`, between the
+    /// start and end frame
+    pub(crate) fn write_encoded<B>(&self, bus: &mut B, frames: &[[u8; 4]]) -> Result<(), B::Error>
+    where
+        B: ClockedLedBus,
+    {
+        let mut chunked = Chunked::new(bus);
+        chunked.fill(0x00, self.start_frame_length as usize)?;
+        chunked.finish()?;
+        bus.write_bytes(frames.as_flattened())?;
+        let mut chunked = Chunked::new(bus);
+        chunked.fill(0x00, self.chip_variant.reset_frame_length())?;
+        chunked.fill(
+            end_frame_byte(self.invert_end_frame),
+            self.end_frame_len(frames.len()),
+        )?;
+        chunked.finish()
+    }
+
+    /// The pixels of a frame, padded or cut to the length of the strip if
+    /// enabled with `set_fit_to_num_leds`
+    fn fit<T, I>(&self, iterator: T) -> impl Iterator<Item = Apa102Pixel>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        let num_leds = self.num_leds.filter(|_| self.fit_to_num_leds);
+        let padding = num_leds
+            .into_iter()
+            .flat_map(|_| repeat(Apa102Pixel::default()));
+        iterator
+            .into_iter()
+            .map(Into::into)
+            .chain(padding)
+            .take(num_leds.unwrap_or(usize::MAX))
+    }
+
+    /// Length of the end frame following `num_leds` pixels
+    ///
+    /// Every led delays the clock by half a cycle, so at least one bit per two
+    /// leds is needed to push the data to the end of the strip. This is based
+    /// on the leds written in the current frame, or the configured strip
+    /// length if that is longer.
+    pub fn end_frame_len(&self, num_leds: usize) -> usize {
+        let num_leds = num_leds.max(self.num_leds.unwrap_or(0));
+        let needed = num_leds.div_ceil(16) * self.end_frame_multiplier as usize;
+        (self.end_frame_length as usize).max(needed) + self.extra_end_frame_length as usize
+    }
+
+    /// Encode a single pixel frame the same way a whole frame is encoded,
+    /// applying all settings like the global brightness
+    ///
+    /// The correction of `set_calibration` and the segments depend on the
+    /// position of the pixel, and are only applied by `encode_pixel_at`.
+    pub fn encode_pixel(&self, pixel: Apa102Pixel) -> [u8; 4] {
+        self.encode_pixel_with(pixel, None, None)
+    }
+
+    /// Encode the pixel frame at `index` of a frame, like `encode_pixel`
+    /// including the correction of `set_calibration` and the settings of the
+    /// segment it is in
+    pub fn encode_pixel_at(&self, index: usize, pixel: Apa102Pixel) -> [u8; 4] {
+        self.encode_pixel_with(pixel, Some(index), None)
+    }
+
+    fn encode_pixel_with(
+        &self,
+        pixel: Apa102Pixel,
+        index: Option<usize>,
+        brightness: Option<u8>,
+    ) -> [u8; 4] {
+        let calibration = index
+            .and_then(|index| self.calibration?.get(index))
+            .copied()
+            .unwrap_or(UNCORRECTED);
+        let segment = index.and_then(|index| {
+            self.segments
+                .iter()
+                .find(|segment| segment.range().contains(&index))
+        });
+        let pixel_order = segment
+            .and_then(|segment| segment.pixel_order())
+            .unwrap_or(self.pixel_order);
+        let brightness_strategy = segment
+            .and_then(|segment| segment.brightness_strategy())
+            .unwrap_or(self.brightness_strategy());
+        let pixel = match self.color_conversion {
+            Some(color_conversion) => {
+                let converted = color_conversion(pixel.color);
+                Apa102Pixel {
+                    color: converted.color,
+                    brightness: scale8(converted.brightness, pixel.brightness),
+                    five_bit: pixel.five_bit.or(converted.five_bit),
+                }
+            }
+            None => pixel,
+        };
+        let item = pixel.color;
+        let item = match self.gamma {
+            Some(gamma) => gamma.apply(item),
+            None => item,
+        };
+        let video = self.video_scaling;
+        let scale = |value, scale| match video {
+            true => scale8_video(value, scale),
+            false => scale8(value, scale),
+        };
+        let scale_color = |color, brightness| match video {
+            true => scale_color_video(color, brightness),
+            false => scale_color(color, brightness),
+        };
+        let (correction, temperature) = (self.color_correction, self.color_temperature.0);
+        let compensation = self.compensation;
+        let correct = |c, correction, temperature, compensation, calibration| {
+            let correction = scale8(scale8(correction, temperature), compensation);
+            scale(c, scale8(correction, calibration))
+        };
+        let item = RGB8::new(
+            correct(
+                item.r,
+                correction.r,
+                temperature.r,
+                compensation.r,
+                calibration.r,
+            ),
+            correct(
+                item.g,
+                correction.g,
+                temperature.g,
+                compensation.g,
+                calibration.g,
+            ),
+            correct(
+                item.b,
+                correction.b,
+                temperature.b,
+                compensation.b,
+                calibration.b,
+            ),
+        );
+        let global = brightness.unwrap_or(self.brightness);
+        let global = match self.brightness_ramp {
+            Some(_) => global.min(self.ramped_brightness),
+            None => global,
+        };
+        let global = scale8(global, self.derating_scale);
+        let combined = scale(global, pixel.brightness);
+        let brightness = match self.brightness_curve.apply(combined) {
+            0 if video && combined != 0 => 1,
+            brightness => brightness,
+        };
+        let fixed = match pixel.five_bit {
+            Some(five_bit) => Some(five_bit.get()),
+            None => self.global_current.map(U5::get),
+        };
+        let (header, item) = match (fixed, brightness_strategy) {
+            (Some(header), _) => (header, scale_color(item, brightness)),
+            (None, BrightnessStrategy::ScaleColorOnly) => (31, scale_color(item, brightness)),
+            (None, BrightnessStrategy::HardwareFiveBit) if self.dithering => {
+                // Bit reversing the frame counter spreads the phases evenly
+                // over any number of consecutive frames
+                let phase = self.frame.reverse_bits();
+                (five_bit_dithered(brightness, phase), item)
+            }
+            (None, BrightnessStrategy::HardwareFiveBit) => (five_bit(brightness), item),
+            (None, BrightnessStrategy::Pseudo13) if video => {
+                let (five_bit, scaled) = pseudo13(item, brightness);
+                let lit = |scaled: u8, c: u8| scaled.max(u8::from(c != 0 && brightness != 0));
+                let scaled = RGB8::new(
+                    lit(scaled.r, item.r),
+                    lit(scaled.g, item.g),
+                    lit(scaled.b, item.b),
+                );
+                (five_bit, scaled)
+            }
+            (None, BrightnessStrategy::Pseudo13) => pseudo13(item, brightness),
+        };
+        let floor = self.five_bit_floor.min(31);
+        let (header, item) = match fixed {
+            None if header < floor => {
+                // Move the rest of the dimming into the colors
+                let channel =
+                    |c: u8| ((c as u16 * header as u16 + floor as u16 / 2) / floor as u16) as u8;
+                (
+                    floor,
+                    RGB8::new(channel(item.r), channel(item.g), channel(item.b)),
+                )
+            }
+            _ => (header, item),
+        };
+        let header = 0xE0 | header;
+        let [a, b, c] = pixel_order.order(item.r, item.g, item.b);
+        [header, a, b, c]
+    }
+
+    /// Decode a single pixel frame
+    ///
+    /// The channels are put back in RGB order and the 5 bit brightness field is
+    /// scaled to the 8 bit brightness. Color correction, conversion and
+    /// scaling applied while encoding can't be undone.
+    pub fn decode_pixel(&self, frame: [u8; 4]) -> Apa102Pixel {
+        let [r, g, b] = self.pixel_order.unorder([frame[1], frame[2], frame[3]]);
+        let five_bit = frame[0] & 0x1F;
+        Apa102Pixel::new(r, g, b, (five_bit as u16 * 255 / 31) as u8)
+    }
+}
+
+impl Default for Apa102Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pixel frame turning off a led no pixel is placed on by the index map
+pub(crate) const UNMAPPED_FRAME: [u8; 4] = [0xE0, 0, 0, 0];
+
+/// Position on the strip of the pixel at `index`, `None` if it is past the
+/// end of `index_map`
+pub(crate) fn position(index_map: Option<&[usize]>, index: usize) -> Option<usize> {
+    match index_map {
+        Some(index_map) => index_map.get(index).copied(),
+        None => Some(index),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::RecordingBus;
+
+    const COLOR: RGB8 = RGB8::new(200, 100, 0);
+
+    /// A complete encoded frame of `pixels`
+    fn encode(encoder: &Apa102Encoder, pixels: &[Apa102Pixel]) -> Vec<u8> {
+        let mut buffer = [0; 512];
+        let len = encoder.encode(pixels.iter().copied(), &mut buffer).unwrap();
+        buffer[..len].to_vec()
+    }
+
+    /// The pixel frame of `pixel` encoded alone
+    fn encode_one(encoder: &Apa102Encoder, pixel: impl Into<Apa102Pixel>) -> [u8; 4] {
+        encode(encoder, &[pixel.into()])[4..8].try_into().unwrap()
+    }
+
+    fn with_strategy(brightness_strategy: BrightnessStrategy) -> Apa102Encoder {
+        let mut encoder = Apa102Encoder::new();
+        encoder.set_brightness_strategy(brightness_strategy);
+        encoder.set_brightness(128);
+        encoder
+    }
+
+    #[test]
+    fn encodes_start_pixel_and_end_frames() {
+        let encoder = Apa102Encoder::new();
+        let bytes = encode(&encoder, &[RGB8::new(1, 2, 3).into()]);
+        assert_eq!(bytes, [0, 0, 0, 0, 0xFF, 3, 2, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn sizes_end_frame_for_the_strip() {
+        let mut encoder = Apa102Encoder::new();
+        encoder.set_invert_end_frame(false);
+        let pixels = [Apa102Pixel::default(); 100];
+        let bytes = encode(&encoder, &pixels);
+        assert_eq!(bytes.len(), encoder.frame_len(100));
+        assert_eq!(bytes[404..], [0xFF; 7]);
+
+        encoder.set_end_frame_multiplier(2);
+        encoder.set_extra_end_frame_length(3);
+        assert_eq!(encode(&encoder, &pixels).len(), 404 + 14 + 3);
+
+        // Sized for the whole strip even when fewer leds are written
+        encoder.set_num_leds(Some(200));
+        assert_eq!(encode(&encoder, &pixels[..1]).len(), 8 + 26 + 3);
+    }
+
+    #[test]
+    fn sends_reset_frame_to_sk9822() {
+        let encoder = Apa102Encoder::new_with_chip_variant(ChipVariant::Sk9822);
+        let bytes = encode(&encoder, &[RGB8::new(1, 2, 3).into()]);
+        assert_eq!(bytes, [0, 0, 0, 0, 0xFF, 3, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn applies_brightness_strategies() {
+        let encoder = with_strategy(BrightnessStrategy::ScaleColorOnly);
+        assert_eq!(encode_one(&encoder, COLOR), [0xFF, 0, 50, 100]);
+        let encoder = with_strategy(BrightnessStrategy::HardwareFiveBit);
+        assert_eq!(encode_one(&encoder, COLOR), [0xF0, 0, 100, 200]);
+        let encoder = with_strategy(BrightnessStrategy::Pseudo13);
+        assert_eq!(encode_one(&encoder, COLOR), [0xED, 0, 120, 239]);
+    }
+
+    #[test]
+    fn sends_fixed_five_bit_values() {
+        let mut encoder = with_strategy(BrightnessStrategy::ScaleColorOnly);
+        let five_bit = U5::new(5).unwrap();
+        assert_eq!(encode_one(&encoder, (COLOR, five_bit)), [0xE5, 0, 50, 100]);
+        encoder.set_global_current(U5::new(7));
+        assert_eq!(encode_one(&encoder, COLOR), [0xE7, 0, 50, 100]);
+    }
+
+    #[test]
+    fn moves_dimming_below_five_bit_floor_into_colors() {
+        let mut encoder = with_strategy(BrightnessStrategy::HardwareFiveBit);
+        encoder.set_brightness(16);
+        assert_eq!(encode_one(&encoder, COLOR), [0xE2, 0, 100, 200]);
+        encoder.set_five_bit_floor(8);
+        assert_eq!(encode_one(&encoder, COLOR), [0xE8, 0, 25, 50]);
+    }
+
+    #[test]
+    fn dithers_five_bit_brightness_over_frames() {
+        let mut encoder = with_strategy(BrightnessStrategy::HardwareFiveBit);
+        encoder.set_brightness(12);
+        encoder.set_dithering(true);
+        assert_eq!(encode_one(&encoder, COLOR), [0xE2, 0, 100, 200]);
+        encoder.next_frame();
+        assert_eq!(encode_one(&encoder, COLOR), [0xE1, 0, 100, 200]);
+    }
+
+    #[test]
+    fn keeps_dim_channels_lit_with_video_scaling() {
+        let mut encoder = Apa102Encoder::new();
+        encoder.set_brightness(1);
+        let color = RGB8::new(200, 1, 0);
+        assert_eq!(encode_one(&encoder, color), [0xFF, 0, 0, 1]);
+        encoder.set_video_scaling(true);
+        assert_eq!(encode_one(&encoder, color), [0xFF, 0, 1, 1]);
+    }
+
+    #[test]
+    fn ramps_brightness_up_over_frames() {
+        let mut encoder = Apa102Encoder::new();
+        encoder.set_brightness_ramp(Some(100));
+        let mut reds = Vec::new();
+        for _ in 0..4 {
+            reds.push(encode_one(&encoder, COLOR)[3]);
+            encoder.next_frame();
+        }
+        assert_eq!(reds, [0, 78, 157, 200]);
+    }
+
+    #[test]
+    fn places_pixels_following_index_map() {
+        let encoder = Apa102Encoder::new();
+        let pixels = [RGB8::new(1, 0, 0), RGB8::new(2, 0, 0), RGB8::new(3, 0, 0)];
+        let mut buffer = [0; 32];
+        let len = encoder
+            .encode_with(pixels, &mut buffer, None, Some(&[2, 0]))
+            .unwrap();
+        assert_eq!(
+            buffer[..len],
+            [0, 0, 0, 0, 0xFF, 0, 0, 2, 0xE0, 0, 0, 0, 0xFF, 0, 0, 1, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn encodes_segments_with_their_settings() {
+        const SEGMENTS: [Segment; 2] = [
+            Segment::new("bgr", 0, 1),
+            Segment::new("rgb", 1, 1)
+                .with_pixel_order(PixelOrder::RGB)
+                .with_brightness_strategy(BrightnessStrategy::HardwareFiveBit),
+        ];
+        let mut encoder = with_strategy(BrightnessStrategy::ScaleColorOnly);
+        encoder.set_segments(&SEGMENTS);
+        let bytes = encode(&encoder, &[COLOR.into(), COLOR.into()]);
+        assert_eq!(bytes[4..12], [0xFF, 0, 50, 100, 0xF0, 200, 100, 0]);
+    }
+
+    #[test]
+    fn applies_calibration_by_position() {
+        const CALIBRATION: [RGB8; 1] = [RGB8::new(128, 255, 0)];
+        let mut encoder = Apa102Encoder::new();
+        encoder.set_calibration(Some(&CALIBRATION));
+        let gray = RGB8::new(200, 200, 200);
+        let bytes = encode(&encoder, &[gray.into(), gray.into()]);
+        assert_eq!(bytes[4..12], [0xFF, 0, 200, 100, 0xFF, 200, 200, 200]);
+    }
+
+    #[test]
+    fn writes_frames_like_encode() {
+        let mut encoder = with_strategy(BrightnessStrategy::Pseudo13);
+        encoder.set_invert_end_frame(false);
+        encoder.set_num_leds(Some(100));
+        encoder.set_fit_to_num_leds(true);
+        let pixels = [Apa102Pixel::from(COLOR); 40];
+        let mut bus = RecordingBus::default();
+        encoder.write_frame(&mut bus, pixels, None).unwrap();
+        assert_eq!(bus.bytes, encode(&encoder, &pixels));
+        assert_eq!(bus.bytes.len(), encoder.frame_len(100));
+
+        let mut bus = RecordingBus::default();
+        encoder.write_frame(&mut bus, pixels, Some(255)).unwrap();
+        encoder.set_brightness(255);
+        assert_eq!(bus.bytes, encode(&encoder, &pixels));
+    }
+}
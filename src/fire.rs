@@ -0,0 +1,110 @@
+use smart_leds_trait::RGB8;
+
+use crate::math::scale8_video;
+use crate::Apa102Pixel;
+
+/// Map a temperature to a black body like color, matching `HeatColor` of
+/// FastLED
+///
+/// The colors go from black through red and yellow to white.
+pub fn heat_color(temperature: u8) -> Apa102Pixel {
+    let t192 = scale8_video(temperature, 191);
+    // Ramps up from 0 to 252 in each third of the range
+    let ramp = (t192 & 0x3F) << 2;
+    let color = if t192 & 0x80 != 0 {
+        RGB8::new(255, 255, ramp)
+    } else if t192 & 0x40 != 0 {
+        RGB8::new(255, ramp, 0)
+    } else {
+        RGB8::new(ramp, 0, 0)
+    };
+    color.into()
+}
+
+/// A fire simulation for a strip of `N` leds, like the Fire2012 example of
+/// FastLED
+///
+/// Every call to `step` advances the simulation and renders it into a
+/// frame, with the base of the fire at the first led. It should be called
+/// at a steady rate, around 60 times a second.
+pub struct Fire<const N: usize> {
+    heat: [u8; N],
+    cooling: u8,
+    sparking: u8,
+    seed: u16,
+}
+
+impl<const N: usize> Fire<N> {
+    /// new constructs a fire with the same parameters as Fire2012: a
+    /// `cooling` of 55 and a `sparking` of 120
+    pub fn new() -> Self {
+        Self::new_with_options(55, 120)
+    }
+
+    /// `cooling` controls how fast the flames cool down, which makes them
+    /// shorter, usually between 20 and 100. `sparking` is the chance out of
+    /// 255 of a new spark every step, usually between 50 and 200.
+    pub fn new_with_options(cooling: u8, sparking: u8) -> Self {
+        Self {
+            heat: [0; N],
+            cooling,
+            sparking,
+            seed: 1337,
+        }
+    }
+
+    /// Seed the random numbers, e.g. to not have every fire look the same
+    pub fn set_seed(&mut self, seed: u16) {
+        self.seed = seed;
+    }
+
+    /// Advance the simulation and render it into `frame`
+    ///
+    /// Leds beyond `N` are left untouched.
+    pub fn step(&mut self, frame: &mut [Apa102Pixel]) {
+        if N == 0 {
+            return;
+        }
+
+        // Cool down every cell a little
+        let max_cooling = (self.cooling as usize * 10 / N + 2).min(255) as u8;
+        for i in 0..N {
+            let cooling = self.random8_below(max_cooling);
+            self.heat[i] = self.heat[i].saturating_sub(cooling);
+        }
+
+        // Heat drifts up and diffuses a little
+        for k in (2..N).rev() {
+            let heat = self.heat[k - 1] as u16 + 2 * self.heat[k - 2] as u16;
+            self.heat[k] = (heat / 3) as u8;
+        }
+
+        // Randomly ignite new sparks near the bottom
+        if self.random8() < self.sparking {
+            let y = self.random8_below(N.min(7) as u8) as usize;
+            let spark = 160 + self.random8_below(95);
+            self.heat[y] = self.heat[y].saturating_add(spark);
+        }
+
+        for (pixel, &heat) in frame.iter_mut().zip(self.heat.iter()) {
+            *pixel = heat_color(heat);
+        }
+    }
+
+    /// FastLED's 16 bit linear congruential generator
+    fn random8(&mut self) -> u8 {
+        self.seed = self.seed.wrapping_mul(2053).wrapping_add(13849);
+        let [hi, lo] = self.seed.to_be_bytes();
+        hi.wrapping_add(lo)
+    }
+
+    fn random8_below(&mut self, limit: u8) -> u8 {
+        ((self.random8() as u16 * limit as u16) >> 8) as u8
+    }
+}
+
+impl<const N: usize> Default for Fire<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,54 @@
+use crate::Apa102Pixel;
+
+/// The pixels of a whole strip, checked to be as many as it has leds
+///
+/// Writing fewer pixels than a strip has leaves the leds at its end lit in
+/// their old colors. A `Frame` is created for the number of leds set with
+/// `Apa102Encoder::set_num_leds`, see `Apa102::frame`, and keeps its length:
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, Apa102Pixel, ClockedLedBus};
+/// # use smart_leds_trait::{SmartLedsWrite, RGB8};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # const NUM_LEDS: usize = 60;
+/// # const RED: RGB8 = RGB8::new(255, 0, 0);
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let mut pixels = [Apa102Pixel::default(); NUM_LEDS];
+/// apa102.encoder_mut().set_num_leds(Some(NUM_LEDS));
+/// let mut frame = apa102.frame(&mut pixels).unwrap();
+/// frame.pixels_mut()[0] = RED.into();
+/// apa102.write(frame.pixels())?;
+/// # Ok::<(), ()>(())
+/// ```
+pub struct Frame<'a> {
+    pixels: &'a mut [Apa102Pixel],
+}
+
+impl<'a> Frame<'a> {
+    /// new wraps `pixels` for a strip of `num_leds` leds, `None` if their
+    /// number doesn't match
+    pub fn new(pixels: &'a mut [Apa102Pixel], num_leds: usize) -> Option<Frame<'a>> {
+        (pixels.len() == num_leds).then_some(Self { pixels })
+    }
+
+    /// The pixels of the frame, to be written to the strip
+    pub fn pixels(&self) -> impl Iterator<Item = Apa102Pixel> + '_ {
+        self.pixels.iter().copied()
+    }
+
+    /// Borrow the pixels to change them
+    pub fn pixels_mut(&mut self) -> &mut [Apa102Pixel] {
+        self.pixels
+    }
+
+    /// Number of leds of the strip the frame is for
+    pub fn num_leds(&self) -> usize {
+        self.pixels.len()
+    }
+}
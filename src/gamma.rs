@@ -0,0 +1,316 @@
+use smart_leds_trait::RGB8;
+
+use crate::math::{powf, scale8};
+use crate::Apa102Pixel;
+
+/// A lookup table mapping color values through a gamma curve
+///
+/// Leds are linear, while the eye is more sensitive to changes of dim
+/// colors. Without gamma correction fades look like they rush through the
+/// dark end and barely change at the bright end.
+///
+/// The common exponents are available as constants, others can be built
+/// at compile time:
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, ClockedLedBus, Gamma, GammaTable};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// static GAMMA_2_4: GammaTable = GammaTable::new(2.4);
+/// apa102.encoder_mut().set_gamma(Some(Gamma::new_per_channel(
+///     &GammaTable::GAMMA_2_8,
+///     &GammaTable::GAMMA_2_8,
+///     &GAMMA_2_4,
+/// )));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GammaTable(pub [u8; 256]);
+
+impl GammaTable {
+    /// Leaves values unchanged
+    pub const LINEAR: GammaTable = GammaTable::new(1.0);
+    /// The gamma of sRGB displays, for colors picked on a screen
+    pub const GAMMA_2_2: GammaTable = GammaTable::new(2.2);
+    pub const GAMMA_2_5: GammaTable = GammaTable::new(2.5);
+    /// The most common curve for leds, used by Adafruit and others
+    pub const GAMMA_2_8: GammaTable = GammaTable::new(2.8);
+    /// The exact sRGB transfer function (IEC 61966-2-1), for video and
+    /// ambilight content
+    pub const SRGB: GammaTable = GammaTable::srgb();
+
+    /// Build the table for `value^exponent`, with values scaled to 0.0..=1.0
+    ///
+    /// Meant to be evaluated at compile time, in a `const` or `static`.
+    pub const fn new(exponent: f32) -> GammaTable {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let value = powf(i as f32 / 255.0, exponent);
+            table[i] = (value * 255.0 + 0.5) as u8;
+            i += 1;
+        }
+        GammaTable(table)
+    }
+
+    /// Build the table for the piecewise sRGB transfer function, linear near
+    /// black and a 2.4 power curve above
+    pub const fn srgb() -> GammaTable {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let value = srgb_to_linear(i as f32 / 255.0);
+            table[i] = (value * 255.0 + 0.5) as u8;
+            i += 1;
+        }
+        GammaTable(table)
+    }
+
+    /// Copy the table into `ram` once at startup, for use in a `Gamma`
+    ///
+    /// Tables in flash can stall lookups on chips that execute from flash
+    /// through a cache, like the ESP32 or RP2040, when the cache misses in
+    /// the middle of writing a frame.
+    ///
+    /// ```no_run
+    /// # use apa102_spi::{Apa102, ClockedLedBus, Gamma, GammaTable};
+    /// # struct Spi;
+    /// # impl ClockedLedBus for Spi {
+    /// #     type Error = ();
+    /// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// # mod cortex_m {
+    /// #     macro_rules! singleton {
+    /// #         (: $ty:ty = $expr:expr) => {
+    /// #             Some(Box::leak(Box::new($expr)))
+    /// #         };
+    /// #     }
+    /// #     pub(crate) use singleton;
+    /// # }
+    /// # let mut apa102 = Apa102::new(Spi);
+    /// let ram = cortex_m::singleton!(: GammaTable = GammaTable([0; 256])).unwrap();
+    /// apa102.encoder_mut().set_gamma(Some(Gamma::new(GammaTable::GAMMA_2_8.copy_to(ram))));
+    /// ```
+    pub fn copy_to(&self, ram: &'static mut GammaTable) -> &'static GammaTable {
+        *ram = *self;
+        ram
+    }
+
+    /// Map a single value through the table
+    pub fn apply(&self, value: u8) -> u8 {
+        self.0[value as usize]
+    }
+}
+
+/// Gamma curves for the three color channels
+///
+/// Leds often respond differently on each channel, e.g. blue tends to need
+/// a lower exponent than red and green.
+#[derive(Clone, Copy, Debug)]
+pub struct Gamma {
+    r: Curve,
+    g: Curve,
+    b: Curve,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Curve {
+    Table(&'static GammaTable),
+    /// `gamma_2_8` computed for every value instead of looked up
+    #[cfg(feature = "runtime-gamma")]
+    Computed2_8,
+}
+
+impl Curve {
+    fn apply(&self, value: u8) -> u8 {
+        match self {
+            Curve::Table(table) => table.apply(value),
+            #[cfg(feature = "runtime-gamma")]
+            Curve::Computed2_8 => gamma_2_8(value),
+        }
+    }
+}
+
+impl Gamma {
+    pub const GAMMA_2_2: Gamma = Gamma::new(&GammaTable::GAMMA_2_2);
+    pub const GAMMA_2_5: Gamma = Gamma::new(&GammaTable::GAMMA_2_5);
+    /// With the `runtime-gamma` feature, this computes the curve instead of
+    /// using `GammaTable::GAMMA_2_8`, saving the flash taken by the table.
+    /// The result is off by at most one from the table.
+    #[cfg(not(feature = "runtime-gamma"))]
+    pub const GAMMA_2_8: Gamma = Gamma::new(&GammaTable::GAMMA_2_8);
+    #[cfg(feature = "runtime-gamma")]
+    pub const GAMMA_2_8: Gamma = Gamma {
+        r: Curve::Computed2_8,
+        g: Curve::Computed2_8,
+        b: Curve::Computed2_8,
+    };
+    pub const SRGB: Gamma = Gamma::new(&GammaTable::SRGB);
+
+    /// Use the same curve for all channels
+    pub const fn new(table: &'static GammaTable) -> Gamma {
+        Gamma {
+            r: Curve::Table(table),
+            g: Curve::Table(table),
+            b: Curve::Table(table),
+        }
+    }
+
+    /// Use a separate curve for each channel
+    pub const fn new_per_channel(
+        r: &'static GammaTable,
+        g: &'static GammaTable,
+        b: &'static GammaTable,
+    ) -> Gamma {
+        Gamma {
+            r: Curve::Table(r),
+            g: Curve::Table(g),
+            b: Curve::Table(b),
+        }
+    }
+
+    /// Map a color through the curves
+    pub fn apply(&self, color: RGB8) -> RGB8 {
+        RGB8::new(
+            self.r.apply(color.r),
+            self.g.apply(color.g),
+            self.b.apply(color.b),
+        )
+    }
+}
+
+/// An iterator adaptor turning `RGB8` colors into gamma and color corrected
+/// `Apa102Pixel`s at a given brightness
+///
+/// Composes with other iterator adaptors and can be passed straight to
+/// `write`, without collecting the pixels first:
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, ClockedLedBus, GammaBrightness, TYPICAL_SMD5050};
+/// # use smart_leds_trait::{SmartLedsWrite, RGB8};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let colors = [RGB8::default(); 60];
+/// let pixels = GammaBrightness::new(colors.iter().copied(), 64, TYPICAL_SMD5050);
+/// apa102.write(pixels)?;
+/// # Ok::<(), ()>(())
+/// ```
+///
+/// The colors are mapped through `Gamma::GAMMA_2_8` and scaled by
+/// `color_correction`, like `set_gamma` and `set_color_correction` do for a
+/// whole controller. The brightness is kept apart from the colors, so the
+/// controller applies it with its `BrightnessStrategy`, e.g. in the 5 bit
+/// brightness field.
+#[derive(Clone, Debug)]
+pub struct GammaBrightness<I> {
+    iter: I,
+    brightness: u8,
+    color_correction: RGB8,
+}
+
+impl<I> GammaBrightness<I>
+where
+    I: Iterator<Item = RGB8>,
+{
+    pub fn new(iter: I, brightness: u8, color_correction: RGB8) -> GammaBrightness<I> {
+        Self {
+            iter,
+            brightness,
+            color_correction,
+        }
+    }
+}
+
+impl<I> Iterator for GammaBrightness<I>
+where
+    I: Iterator<Item = RGB8>,
+{
+    type Item = Apa102Pixel;
+
+    fn next(&mut self) -> Option<Apa102Pixel> {
+        let color = Gamma::GAMMA_2_8.apply(self.iter.next()?);
+        let correction = self.color_correction;
+        let color = RGB8::new(
+            scale8(color.r, correction.r),
+            scale8(color.g, correction.g),
+            scale8(color.b, correction.b),
+        );
+        Some(Apa102Pixel::from((color, self.brightness)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for GammaBrightness<I> where I: ExactSizeIterator<Item = RGB8> {}
+
+/// Integer approximation of `GammaTable::GAMMA_2_8`, off by at most one
+///
+/// Fits `x^2.8` as a mix of `x^2` and `x^3`, in 32 bit fixed point.
+#[cfg(feature = "runtime-gamma")]
+fn gamma_2_8(value: u8) -> u8 {
+    let x = value as u64;
+    let y = (2_566_311 * x * x + 56_249 * x * x * x + (1 << 31)) >> 32;
+    y.min(255) as u8
+}
+
+/// The sRGB transfer function (IEC 61966-2-1), decoding a channel in
+/// 0.0..=1.0 to linear light
+pub(crate) const fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        powf((c + 0.055) / 1.055, 2.4)
+    }
+}
+
+/// Map a brightness through the CIE 1931 lightness curve
+///
+/// The brightness is taken as lightness L* from 0 to 100 and converted to
+/// relative luminance.
+pub(crate) fn cie1931(brightness: u8) -> u8 {
+    // Everything is scaled by 255 * 255 to stay in integers, so the
+    // lightness l is 100 * brightness / 255
+    let l = 100 * brightness as u64;
+    let luminance = if l <= 8 * 255 {
+        // 255 * (l / 255) / 903.3
+        (l * 10 + 4516) / 9033
+    } else {
+        // 255 * ((l / 255 + 16) / 116)^3
+        let n = l + 16 * 255;
+        let d = 116 * 255;
+        (255 * n * n * n + d * d * d / 2) / (d * d * d)
+    };
+    luminance as u8
+}
+
+#[cfg(all(test, feature = "runtime-gamma"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamma_2_8_matches_table() {
+        for value in 0..=255 {
+            let table = GammaTable::GAMMA_2_8.apply(value);
+            let computed = gamma_2_8(value);
+            assert!(
+                computed.abs_diff(table) <= 1,
+                "{value}: computed {computed}, table {table}"
+            );
+        }
+    }
+}
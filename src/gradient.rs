@@ -0,0 +1,71 @@
+use smart_leds_trait::RGB8;
+
+use crate::math::lerp_rgb8;
+use crate::Apa102Pixel;
+
+/// A color gradient through a list of `(position, color)` keyframes
+///
+/// The keyframes have to be sorted by position, which goes from 0 at the
+/// start of the gradient to 255 at its end. Colors are mixed linearly
+/// between keyframes and held before the first and after the last one.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, ClockedLedBus, Gradient};
+/// # use smart_leds_trait::{SmartLedsWrite, RGB8};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// const SUNSET: Gradient = Gradient::new(&[
+///     (0, RGB8::new(255, 0, 0)),
+///     (128, RGB8::new(255, 128, 0)),
+///     (255, RGB8::new(0, 0, 64)),
+/// ]);
+/// apa102.write(SUNSET.sample(60, 0))?;
+/// # Ok::<(), ()>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Gradient<'a> {
+    keyframes: &'a [(u8, RGB8)],
+}
+
+impl<'a> Gradient<'a> {
+    pub const fn new(keyframes: &'a [(u8, RGB8)]) -> Self {
+        Self { keyframes }
+    }
+
+    /// Color of the gradient at `position`, black if there are no keyframes
+    pub fn color_at(&self, position: u8) -> RGB8 {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|&(keyframe, _)| keyframe >= position);
+        match next {
+            None => self.keyframes.last().map_or(RGB8::default(), |k| k.1),
+            Some(0) => self.keyframes[0].1,
+            Some(i) => {
+                let (start, from) = self.keyframes[i - 1];
+                let (end, to) = self.keyframes[i];
+                let amount = (position - start) as u16 * 255 / (end - start) as u16;
+                lerp_rgb8(from, to, amount as u8)
+            }
+        }
+    }
+
+    /// Spread the gradient over `num_leds` pixels, shifted by `offset`
+    ///
+    /// Increasing `offset` every frame scrolls the gradient along the strip.
+    /// The positions wrap around, so to scroll smoothly the first and last
+    /// keyframes should have the same color.
+    pub fn sample(&self, num_leds: usize, offset: u8) -> impl Iterator<Item = Apa102Pixel> + 'a {
+        let gradient = *self;
+        (0..num_leds).map(move |i| {
+            let position = (i * 256 / num_leds) as u8;
+            gradient.color_at(position.wrapping_add(offset)).into()
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use core::convert::Infallible;
+
+use crate::{Apa102Matrix, Apa102Pixel, ClockedLedBus};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::{Rgb565, Rgb888, RgbColor};
+use embedded_graphics_core::Pixel;
+
+impl<SPI, const N: usize> OriginDimensions for Apa102Matrix<SPI, N>
+where
+    SPI: ClockedLedBus,
+{
+    fn size(&self) -> Size {
+        Size::new(self.map().width() as u32, self.map().height() as u32)
+    }
+}
+
+/// Draws into the framebuffer, only written to the leds by `flush()`
+///
+/// Colors are stored as drawn. The settings of the controller, like its gamma
+/// curve and brightness, are applied when flushing.
+impl<SPI, const N: usize> DrawTarget for Apa102Matrix<SPI, N>
+where
+    SPI: ClockedLedBus,
+{
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y)) {
+                self.set_xy(x, y, color);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<Rgb888> for Apa102Pixel {
+    fn from(color: Rgb888) -> Self {
+        Self::new(color.r(), color.g(), color.b(), 255)
+    }
+}
+
+impl From<Rgb565> for Apa102Pixel {
+    /// Scale the 5 and 6 bit channels to 8 bits, so full intensity stays
+    /// full intensity
+    fn from(color: Rgb565) -> Self {
+        let r = color.r() << 3 | color.r() >> 2;
+        let g = color.g() << 2 | color.g() >> 4;
+        let b = color.b() << 3 | color.b() >> 2;
+        Self::new(r, g, b, 255)
+    }
+}
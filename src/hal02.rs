@@ -0,0 +1,21 @@
+use embedded_hal_02::blocking::spi::Write;
+
+use crate::ClockedLedBus;
+
+/// Adapter for SPI peripherals implementing the `embedded-hal` 0.2
+/// `blocking::spi::Write` trait
+///
+/// Wrap the peripheral and pass it to the usual constructors, e.g.
+/// `Apa102::new(Hal02(spi))`.
+pub struct Hal02<SPI>(pub SPI);
+
+impl<SPI> ClockedLedBus for Hal02<SPI>
+where
+    SPI: Write<u8>,
+{
+    type Error = SPI::Error;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SPI::Error> {
+        self.0.write(bytes)
+    }
+}
@@ -0,0 +1,165 @@
+use smart_leds_trait::{SmartLedsWrite, RGB16, RGB8};
+
+use crate::chunked::Chunked;
+use crate::{end_frame_byte, ClockedLedBus, PixelOrder};
+
+/// A pixel of a HD108 led
+///
+/// Every channel has 16 bit color depth and its own 5 bit brightness.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hd108Pixel {
+    pub color: RGB16,
+    /// Brightness of each channel, values above 31 are treated as 31
+    pub brightness: RGB8,
+}
+
+impl From<RGB16> for Hd108Pixel {
+    /// Use the color at full brightness
+    fn from(color: RGB16) -> Self {
+        Self {
+            color,
+            brightness: RGB8::new(31, 31, 31),
+        }
+    }
+}
+
+/// A controller for a series of HD108 LEDs
+///
+/// HD108 leds use the same start/end frame scheme as APA102 leds, but send
+/// 64 bit per pixel: a header with the three 5 bit brightness fields,
+/// followed by three 16 bit color channels.
+pub struct Hd108<SPI> {
+    spi: SPI,
+    end_frame_length: u8,
+    invert_end_frame: bool,
+    pixel_order: PixelOrder,
+}
+
+impl<SPI> Hd108<SPI> {
+    /// new constructs a controller for a series of HD108 LEDs.
+    /// The End Frame defaults to 32 bits of zeroes and the PixelOrder
+    /// to RGB, both can be customized using new_with_options()
+    pub fn new(spi: SPI) -> Hd108<SPI> {
+        Self {
+            spi,
+            end_frame_length: 4,
+            invert_end_frame: true,
+            pixel_order: PixelOrder::RGB,
+        }
+    }
+
+    /// The End Frame options match `Apa102::new_with_options`
+    pub fn new_with_options(
+        spi: SPI,
+        end_frame_length: u8,
+        invert_end_frame: bool,
+        pixel_order: PixelOrder,
+    ) -> Hd108<SPI> {
+        Self {
+            spi,
+            end_frame_length,
+            invert_end_frame,
+            pixel_order,
+        }
+    }
+
+    /// Length of the end frame following `num_leds` pixels
+    ///
+    /// Like for APA102 leds, at least one bit per two leds is needed to push
+    /// the data to the end of the strip, see `Apa102Encoder::end_frame_len`.
+    pub fn end_frame_len(&self, num_leds: usize) -> usize {
+        end_frame_len(self.end_frame_length, num_leds)
+    }
+
+    /// Borrow the SPI, e.g. to change its frequency, without consuming self
+    pub fn bus_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// Free the owned resources consuming self
+    pub fn free(self) -> SPI {
+        self.spi
+    }
+}
+
+/// See `Hd108::end_frame_len`
+fn end_frame_len(end_frame_length: u8, num_leds: usize) -> usize {
+    (end_frame_length as usize).max(num_leds.div_ceil(16))
+}
+
+impl PixelOrder {
+    /// Encode a single HD108 pixel frame
+    fn encode_hd108(&self, pixel: Hd108Pixel) -> [u8; 8] {
+        let b = pixel.brightness;
+        let [b0, b1, b2] = self.order(b.r.min(31), b.g.min(31), b.b.min(31));
+        let header = 0x8000 | (b0 as u16) << 10 | (b1 as u16) << 5 | b2 as u16;
+        let c = pixel.color;
+        let mut frame = [0; 8];
+        frame[..2].copy_from_slice(&header.to_be_bytes());
+        for (bytes, channel) in frame[2..]
+            .chunks_exact_mut(2)
+            .zip(self.order(c.r, c.g, c.b))
+        {
+            bytes.copy_from_slice(&channel.to_be_bytes());
+        }
+        frame
+    }
+}
+
+impl<SPI> SmartLedsWrite for Hd108<SPI>
+where
+    SPI: ClockedLedBus,
+{
+    type Color = Hd108Pixel;
+    type Error = SPI::Error;
+    /// Write all the items of an iterator to a HD108 strip
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), SPI::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let mut chunked = Chunked::new(&mut self.spi);
+        // The HD108 start frame is 128 bits long
+        chunked.fill(0x00, 16)?;
+        let mut num_leds = 0;
+        for item in iterator {
+            chunked.push(&self.pixel_order.encode_hd108(item.into()))?;
+            num_leds += 1;
+        }
+        chunked.fill(
+            end_frame_byte(self.invert_end_frame),
+            end_frame_len(self.end_frame_length, num_leds),
+        )?;
+        chunked.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::RecordingBus;
+
+    #[test]
+    fn encodes_pixels() {
+        let mut hd108 = Hd108::new(RecordingBus::default());
+        let pixel = Hd108Pixel {
+            color: RGB16::new(0x0102, 0x0304, 0x0506),
+            brightness: RGB8::new(1, 2, 40),
+        };
+        hd108.write([pixel]).unwrap();
+        let bytes = hd108.free().bytes;
+        assert_eq!(bytes[..16], [0; 16]);
+        assert_eq!(bytes[16..24], [0x84, 0x5F, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(bytes[24..], [0; 4]);
+    }
+
+    #[test]
+    fn sizes_end_frame_for_the_pixels_written() {
+        let mut hd108 = Hd108::new_with_options(RecordingBus::default(), 4, false, PixelOrder::RGB);
+        hd108.write([Hd108Pixel::default(); 100]).unwrap();
+        assert_eq!(hd108.end_frame_len(100), 7);
+        let bytes = hd108.free().bytes;
+        assert_eq!(bytes.len(), 16 + 8 * 100 + 7);
+        assert_eq!(bytes[16 + 8 * 100..], [0xFF; 7]);
+    }
+}
@@ -0,0 +1,116 @@
+use smart_leds_trait::RGB8;
+
+use crate::math::{scale8, scale8_video};
+use crate::{Apa102Pixel, Gamma};
+
+/// A color as hue, saturation and value
+///
+/// The hue goes around the color wheel once from 0 to 255, starting and
+/// ending at red. Conversions to `RGB8` and `Apa102Pixel` use `hsv2rgb`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hsv {
+    pub hue: u8,
+    pub sat: u8,
+    pub val: u8,
+}
+
+impl Hsv {
+    pub const fn new(hue: u8, sat: u8, val: u8) -> Self {
+        Self { hue, sat, val }
+    }
+}
+
+/// Convert a color to RGB, matching `hsv2rgb_rainbow` of FastLED
+///
+/// Unlike a plain spectrum conversion, yellow and orange get a wider range
+/// of hues and every hue has roughly the same brightness.
+pub fn hsv2rgb(hsv: Hsv) -> RGB8 {
+    let offset8 = (hsv.hue & 0x1F) << 3;
+    let third = scale8(offset8, 85);
+    let two_thirds = scale8(offset8, 170);
+
+    let (r, g, b) = match hsv.hue >> 5 {
+        // Red to orange
+        0 => (255 - third, third, 0),
+        // Orange to yellow
+        1 => (171, 85 + third, 0),
+        // Yellow to green
+        2 => (171 - two_thirds, 170 + third, 0),
+        // Green to aqua
+        3 => (0, 255 - third, third),
+        // Aqua to blue
+        4 => (0, 171 - two_thirds, 85 + two_thirds),
+        // Blue to purple
+        5 => (third, 0, 255 - third),
+        // Purple to pink
+        6 => (85 + third, 0, 171 - third),
+        // Pink to red
+        _ => (170 + third, 0, 85 - third),
+    };
+
+    let (r, g, b) = match hsv.sat {
+        255 => (r, g, b),
+        0 => (255, 255, 255),
+        sat => {
+            // Desaturate towards white, keeping the brightness constant
+            let desat = scale8_video(255 - sat, 255 - sat);
+            let scale = 255 - desat;
+            (
+                scale8(r, scale) + desat,
+                scale8(g, scale) + desat,
+                scale8(b, scale) + desat,
+            )
+        }
+    };
+
+    match hsv.val {
+        255 => RGB8::new(r, g, b),
+        val => {
+            let val = scale8_video(val, val);
+            RGB8::new(scale8(r, val), scale8(g, val), scale8(b, val))
+        }
+    }
+}
+
+impl From<Hsv> for RGB8 {
+    fn from(hsv: Hsv) -> Self {
+        hsv2rgb(hsv)
+    }
+}
+
+impl From<Hsv> for Apa102Pixel {
+    fn from(hsv: Hsv) -> Self {
+        hsv2rgb(hsv).into()
+    }
+}
+
+/// Iterate over `num_leds` fully saturated pixels, the hue starting at
+/// `start_hue` and advancing by `delta_hue` from one led to the next
+///
+/// The colors are mapped through `Gamma::GAMMA_2_8`, so don't enable
+/// `set_gamma` on the controller as well. Handy for checking the wiring and
+/// pixel order of a new strip: `apa102.write(rainbow(60, 0, 4))`.
+pub fn rainbow(num_leds: usize, start_hue: u8, delta_hue: u8) -> impl Iterator<Item = Apa102Pixel> {
+    (0..num_leds).map(move |i| {
+        let hue = start_hue.wrapping_add((i as u8).wrapping_mul(delta_hue));
+        Gamma::GAMMA_2_8
+            .apply(Hsv::new(hue, 255, 255).into())
+            .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rainbow_is_gamma_corrected() {
+        let mut pixels = rainbow(2, 0, 64);
+        assert_eq!(pixels.next().unwrap().color, RGB8::new(255, 0, 0));
+        // Hue 64 is yellow, (171, 170, 0) before the 2.8 gamma curve
+        let yellow = pixels.next().unwrap();
+        assert_eq!(yellow.color, RGB8::new(83, 82, 0));
+        assert_eq!(yellow.brightness, 255);
+        assert_eq!(pixels.next(), None);
+    }
+}
@@ -0,0 +1,142 @@
+/// The pixels of `frame` in reverse order, e.g. for a strip mounted the
+/// other way around
+///
+/// ```no_run
+/// # use apa102_spi::{reversed, Apa102, Apa102Pixel, ClockedLedBus};
+/// # use smart_leds_trait::SmartLedsWrite;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let frame = [Apa102Pixel::default(); 60];
+/// apa102.write(reversed(&frame))?;
+/// # Ok::<(), ()>(())
+/// ```
+pub fn reversed<T: Copy>(frame: &[T]) -> impl Iterator<Item = T> + '_ {
+    frame.iter().rev().copied()
+}
+
+/// The pixels of `half_frame` followed by the same pixels in reverse order
+///
+/// Renders symmetric effects on a strip folded back on itself, or spreading
+/// out from its center, from only one half. For a strip with an odd number of
+/// leds, use `mirrored_odd`.
+pub fn mirrored<T: Copy>(half_frame: &[T]) -> impl Iterator<Item = T> + '_ {
+    half_frame.iter().chain(half_frame.iter().rev()).copied()
+}
+
+/// Like `mirrored`, but with the last pixel of `half_frame` only once, in the
+/// center of the strip
+pub fn mirrored_odd<T: Copy>(half_frame: &[T]) -> impl Iterator<Item = T> + '_ {
+    let len = half_frame.len();
+    let back = half_frame.get(..len.saturating_sub(1)).unwrap_or(&[]);
+    half_frame.iter().chain(back.iter().rev()).copied()
+}
+
+/// How the leds of a matrix are wired, row by row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatrixLayout {
+    /// Every row starts on the left
+    Progressive,
+    /// Rows alternate direction, the first one starting on the left. Most
+    /// matrices are wired like this.
+    Serpentine,
+}
+
+/// How a matrix is rotated clockwise from the way it is wired
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatrixRotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// Translates `(x, y)` coordinates on a matrix to the index of the led on the
+/// strip
+///
+/// `width` and `height` are the size of the matrix as wired, the strip
+/// starting at the top left and running along the rows. `(0, 0)` is the top
+/// left after rotating it.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102Pixel, MatrixLayout, MatrixMap};
+/// # use smart_leds_trait::RGB8;
+/// # const RED: RGB8 = RGB8::new(255, 0, 0);
+/// # let mut frame = [Apa102Pixel::default(); 256];
+/// # let (x, y) = (3, 4);
+/// const MAP: MatrixMap = MatrixMap::new(16, 16, MatrixLayout::Serpentine);
+/// frame[MAP.index(x, y).unwrap()] = RED.into();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatrixMap {
+    width: usize,
+    height: usize,
+    layout: MatrixLayout,
+    rotation: MatrixRotation,
+}
+
+impl MatrixMap {
+    pub const fn new(width: usize, height: usize, layout: MatrixLayout) -> Self {
+        Self {
+            width,
+            height,
+            layout,
+            rotation: MatrixRotation::Deg0,
+        }
+    }
+
+    /// Rotate the coordinates by `rotation`
+    pub const fn with_rotation(mut self, rotation: MatrixRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Width after the rotation
+    pub const fn width(&self) -> usize {
+        match self.rotation {
+            MatrixRotation::Deg0 | MatrixRotation::Deg180 => self.width,
+            MatrixRotation::Deg90 | MatrixRotation::Deg270 => self.height,
+        }
+    }
+
+    /// Height after the rotation
+    pub const fn height(&self) -> usize {
+        match self.rotation {
+            MatrixRotation::Deg0 | MatrixRotation::Deg180 => self.height,
+            MatrixRotation::Deg90 | MatrixRotation::Deg270 => self.width,
+        }
+    }
+
+    /// Number of leds of the matrix
+    pub const fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Index on the strip of the led at `(x, y)`, `None` if it is outside of
+    /// the matrix
+    pub const fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x >= self.width() || y >= self.height() {
+            return None;
+        }
+        let (column, row) = match self.rotation {
+            MatrixRotation::Deg0 => (x, y),
+            MatrixRotation::Deg90 => (y, self.height - 1 - x),
+            MatrixRotation::Deg180 => (self.width - 1 - x, self.height - 1 - y),
+            MatrixRotation::Deg270 => (self.width - 1 - y, x),
+        };
+        let column = match self.layout {
+            MatrixLayout::Serpentine if row % 2 == 1 => self.width - 1 - column,
+            _ => column,
+        };
+        Some(row * self.width + column)
+    }
+}
@@ -1,20 +1,122 @@
 //! # Use apa102 leds via spi
 //!
 //! - For usage with `smart-leds`
-//! - Implements the `SmartLedsWrite` trait
+//! - Implements the `SmartLedsWrite` trait, for `Apa102Pixel`s and anything
+//!   converting to them, like plain `RGB8` colors
+//! - `Apa102` works on an `SpiBus` or any other `ClockedLedBus`, e.g. a PIO
+//!   or I2S peripheral
+//! - `Apa102Device` works on an `SpiDevice`, encoding each frame into a
+//!   buffer and sending it in a single transaction, for shared buses
+//! - `Apa102Buffered` keeps a framebuffer, for updating a few pixels at a
+//!   time
 //!
-//! Doesn't use the native brightness settings of the apa102 leds, since that
-//! runs at a much lower pwm frequency and thus nerfes the very high color pwm
-//! frequency. (According to Adafruit)
+//! The settings, like the brightness or the pixel order, are held by the
+//! `Apa102Encoder` of each controller and changed through `encoder_mut()`.
+//! `Apa102Builder` sets them up front and checks them for conflicts.
 //!
-//! Needs a type implementing the `blocking::spi::Write` trait.
+//! Doesn't use the native brightness settings of the apa102 leds by default,
+//! since that runs at a much lower pwm frequency and thus nerfes the very high
+//! color pwm frequency. (According to Adafruit) For leds without this
+//! limitation, like the HD107S, see `BrightnessStrategy`.
 
 #![no_std]
 
-use embedded_hal::spi::SpiBus;
+use core::iter::repeat_n;
+
 use embedded_hal::spi::{Mode, Phase, Polarity};
 
-use smart_leds_trait::{SmartLedsWrite, RGB8};
+use smart_leds_trait::SmartLedsWrite;
+
+mod adalight;
+#[cfg(feature = "animation")]
+mod animation;
+mod blank;
+mod buffered;
+mod builder;
+mod bus;
+mod chunked;
+mod colorutils;
+pub mod conversion;
+mod correction;
+#[cfg(feature = "ddp")]
+mod ddp;
+mod device;
+mod dmx;
+pub mod easing;
+mod encoder;
+mod fire;
+mod frame;
+mod gamma;
+mod gradient;
+#[cfg(feature = "embedded-graphics")]
+mod graphics;
+#[cfg(feature = "embedded-hal-02")]
+mod hal02;
+mod hd108;
+mod hsv;
+mod layout;
+mod lpd8806;
+mod math;
+mod matrix;
+mod noise;
+#[cfg(feature = "oklab")]
+mod oklab;
+mod opc;
+mod palette;
+mod pixel;
+mod player;
+mod power;
+mod segment;
+#[cfg(feature = "embedded-io")]
+mod stream;
+#[cfg(test)]
+mod test_util;
+mod tpm2;
+mod wave;
+mod ws2801;
+
+pub use adalight::AdalightDecoder;
+#[cfg(feature = "animation")]
+pub use animation::{Animator, Breathe, Effect, Rainbow, Scroll};
+pub use blank::BlankOnDrop;
+pub use buffered::Apa102Buffered;
+pub use builder::Apa102Builder;
+pub use bus::ClockedLedBus;
+pub use colorutils::{blend, blend_frames, blend_rgb8, blur1d, fade_to_black_by, nscale8};
+pub use correction::{
+    ColorTemperature, TemperatureCompensation, TYPICAL_8MM_PIXEL, TYPICAL_LED_STRIP,
+    TYPICAL_PIXEL_STRING, TYPICAL_SMD5050, UNCORRECTED,
+};
+#[cfg(feature = "ddp")]
+pub use ddp::DdpPacket;
+pub use device::Apa102Device;
+pub use dmx::{DmxBrightness, DmxMapping};
+pub use encoder::Apa102Encoder;
+pub use fire::{heat_color, Fire};
+pub use frame::Frame;
+pub use gamma::{Gamma, GammaBrightness, GammaTable};
+pub use gradient::Gradient;
+#[cfg(feature = "embedded-hal-02")]
+pub use hal02::Hal02;
+pub use hd108::{Hd108, Hd108Pixel};
+pub use hsv::{hsv2rgb, rainbow, Hsv};
+pub use layout::{mirrored, mirrored_odd, reversed, MatrixLayout, MatrixMap, MatrixRotation};
+pub use lpd8806::Lpd8806;
+pub use matrix::Apa102Matrix;
+pub use noise::{inoise16, inoise16_2d, inoise16_3d, inoise8, inoise8_2d, inoise8_3d};
+#[cfg(feature = "oklab")]
+pub use oklab::{lerp_oklab, lerp_oklch, Oklab, Oklch};
+pub use opc::{OpcDecoder, OpcFrame};
+pub use palette::{color_from_palette, Palette16};
+pub use pixel::{Apa102Pixel, U5};
+pub use player::Player;
+pub use power::{estimate_current_ma, Derating, PowerLimiter, PowerModel};
+pub use segment::{split_segments, Segment};
+#[cfg(feature = "embedded-io")]
+pub use stream::{FrameReader, StreamError};
+pub use tpm2::{Tpm2Decoder, Tpm2NetPacket};
+pub use wave::{beat8, beatsaw8, beatsin8, beattri8, cos8, sin8, triwave8};
+pub use ws2801::{Latch, NoDelay, Ws2801};
 
 /// SPI mode that is needed for this crate
 ///
@@ -24,15 +126,34 @@ pub const MODE: Mode = Mode {
     phase: Phase::CaptureOnFirstTransition,
 };
 
+/// Errors of controllers that need more than the bare SPI error
+#[derive(Debug)]
+pub enum Error<E> {
+    /// The underlying SPI returned an error
+    Spi(E),
+    /// The frame doesn't fit into the supplied buffer
+    BufferTooSmall,
+    /// A frame didn't have as many pixels as the strip has leds
+    WrongLength { expected: usize, actual: usize },
+    /// Options passed to `Apa102Builder` contradict each other:
+    /// `fit_to_num_leds` without `num_leds`, `global_current` with a
+    /// `brightness_strategy`, `five_bit_floor` or `dithering`, `dithering`
+    /// with a strategy other than `HardwareFiveBit`, or `segments` past the
+    /// end of the strip
+    ConflictingOptions,
+    /// The number of leds of the strip is needed but wasn't set with
+    /// `Apa102Encoder::set_num_leds`
+    NumLedsUnset,
+}
+
 pub struct Apa102<SPI> {
     spi: SPI,
-    end_frame_length: u8,
-    invert_end_frame: bool,
-    pixel_order: PixelOrder,
+    encoder: Apa102Encoder,
 }
 
 /// What order to transmit pixel colors. Different Dotstars
 /// need their pixel color data sent in different orders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PixelOrder {
     RGB,
     RBG,
@@ -42,25 +163,134 @@ pub enum PixelOrder {
     BGR, // Default
 }
 
-impl<SPI> Apa102<SPI>
-where
-    SPI: SpiBus,
-{
+/// How the global brightness is applied to the pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrightnessStrategy {
+    /// Scale the color channels and keep the 5 bit brightness field at its
+    /// maximum. The default, since the brightness pwm of APA102 leds runs at
+    /// a much lower frequency than the color pwm.
+    ///
+    /// This never flickers on camera or in POV displays. To trade some of
+    /// that for resolution, use `HardwareFiveBit` or `Pseudo13` with
+    /// `Apa102Encoder::set_five_bit_floor` instead, which keep the 5 bit
+    /// field above a threshold.
+    ScaleColorOnly,
+    /// Put the brightness into the 5 bit brightness field and keep the full
+    /// color resolution. Preferable for leds with a fast brightness pwm,
+    /// like the HD107S.
+    HardwareFiveBit,
+    /// Spread the brightness over both the 5 bit field and the color
+    /// channels, using the lowest 5 bit value the colors still fit in. This
+    /// gives up to 13 bits of resolution for dim colors, at the cost of a low
+    /// frequency brightness pwm on APA102 leds.
+    Pseudo13,
+}
+
+/// How the 8 bit brightness is mapped to light output
+#[derive(Clone, Copy)]
+pub enum BrightnessCurve {
+    /// Light output proportional to the brightness, the default
+    Linear,
+    /// The CIE 1931 lightness curve, making equal brightness steps look
+    /// equally large. Suits fading indicators in and out.
+    Cie1931,
+}
+
+impl BrightnessCurve {
+    pub(crate) fn apply(&self, brightness: u8) -> u8 {
+        match self {
+            BrightnessCurve::Linear => brightness,
+            BrightnessCurve::Cie1931 => gamma::cie1931(brightness),
+        }
+    }
+}
+
+/// The kind of leds driven, to handle their protocol quirks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChipVariant {
+    /// Genuine APA102 leds, the default
+    Apa102,
+    /// SK9822 leds, which need an extra reset frame after the pixel data and
+    /// use the 5 bit field for constant current control. The brightness is
+    /// applied to the colors, see `Apa102Encoder::set_global_current` for
+    /// the current.
+    Sk9822,
+    /// HD107S leds, which run the 5 bit brightness pwm at a high frequency
+    Hd107s,
+}
+
+impl ChipVariant {
+    /// Brightness strategy preferred for this kind of leds
+    pub(crate) fn brightness_strategy(&self) -> BrightnessStrategy {
+        match self {
+            // The 5 bit field of SK9822 leds sets a current rather than a
+            // duty cycle, which doesn't dim linearly
+            ChipVariant::Apa102 | ChipVariant::Sk9822 => BrightnessStrategy::ScaleColorOnly,
+            ChipVariant::Hd107s => BrightnessStrategy::HardwareFiveBit,
+        }
+    }
+
+    /// Length of the reset frame sent between pixel data and end frame
+    pub(crate) fn reset_frame_length(&self) -> usize {
+        match self {
+            ChipVariant::Sk9822 => 4,
+            ChipVariant::Apa102 | ChipVariant::Hd107s => 0,
+        }
+    }
+}
+
+/// Byte the end frame consists of
+pub(crate) fn end_frame_byte(invert_end_frame: bool) -> u8 {
+    match invert_end_frame {
+        false => 0xFF,
+        true => 0x00,
+    }
+}
+
+impl PixelOrder {
+    /// Arrange the red, green and blue values in transmission order
+    pub(crate) fn order<T>(&self, r: T, g: T, b: T) -> [T; 3] {
+        match self {
+            PixelOrder::RGB => [r, g, b],
+            PixelOrder::RBG => [r, b, g],
+            PixelOrder::GRB => [g, r, b],
+            PixelOrder::GBR => [g, b, r],
+            PixelOrder::BRG => [b, r, g],
+            PixelOrder::BGR => [b, g, r],
+        }
+    }
+
+    /// Arrange values in transmission order back into red, green and blue
+    pub(crate) fn unorder<T>(&self, [x, y, z]: [T; 3]) -> [T; 3] {
+        match self {
+            PixelOrder::RGB => [x, y, z],
+            PixelOrder::RBG => [x, z, y],
+            PixelOrder::GRB => [y, x, z],
+            PixelOrder::GBR => [z, x, y],
+            PixelOrder::BRG => [y, z, x],
+            PixelOrder::BGR => [z, y, x],
+        }
+    }
+}
+
+impl<SPI> Apa102<SPI> {
     /// new constructs a controller for a series of APA102 LEDs.
     /// By default, an End Frame consisting of 32 bits of zeroes is emitted
-    /// following the LED data. Control over the size and polarity
+    /// following the LED data, extended to half a bit per LED for strips
+    /// longer than 64 LEDs. Control over the size and polarity
     /// of the End Frame is possible using new_with_options().
     /// PixelOrder defaults to BGR ordering, and can also be customized
     /// using new_with_options()
     pub fn new(spi: SPI) -> Apa102<SPI> {
         Self {
             spi,
-            end_frame_length: 4,
-            invert_end_frame: true,
-            pixel_order: PixelOrder::BGR,
+            encoder: Apa102Encoder::new(),
         }
     }
 
+    /// The End Frame consists of at least `end_frame_length` bytes. These are
+    /// zeroes if `invert_end_frame` is set, or 0xFF otherwise, which some
+    /// boards and clone strips need to latch reliably.
     pub fn new_with_options(
         spi: SPI,
         end_frame_length: u8,
@@ -69,23 +299,169 @@ where
     ) -> Apa102<SPI> {
         Self {
             spi,
-            end_frame_length,
-            invert_end_frame,
-            pixel_order,
+            encoder: Apa102Encoder::new_with_options(
+                end_frame_length,
+                invert_end_frame,
+                pixel_order,
+            ),
+        }
+    }
+
+    /// new_with_chip_variant constructs a controller with the defaults of
+    /// new(), adapted to the quirks of `chip_variant`
+    pub fn new_with_chip_variant(spi: SPI, chip_variant: ChipVariant) -> Apa102<SPI> {
+        Self {
+            spi,
+            encoder: Apa102Encoder::new_with_chip_variant(chip_variant),
         }
     }
 
+    /// with_encoder constructs a controller encoding pixels with `encoder`,
+    /// e.g. one made by `Apa102Builder::build_encoder`
+    pub fn with_encoder(spi: SPI, encoder: Apa102Encoder) -> Apa102<SPI> {
+        Self { spi, encoder }
+    }
+
+    /// The encoder holding the settings of the controller
+    pub fn encoder(&self) -> &Apa102Encoder {
+        &self.encoder
+    }
+
+    /// Borrow the encoder to change the settings of the controller, e.g.
+    /// `apa102.encoder_mut().set_brightness(64)`
+    pub fn encoder_mut(&mut self) -> &mut Apa102Encoder {
+        &mut self.encoder
+    }
+
+    /// The number of leds of the strip, see `Apa102Encoder::num_leds`
+    pub fn num_leds(&self) -> Option<usize> {
+        self.encoder.num_leds()
+    }
+
+    /// The order the colors are sent in, see `Apa102Encoder::pixel_order`
+    pub fn pixel_order(&self) -> PixelOrder {
+        self.encoder.pixel_order()
+    }
+
+    /// Length of the end frame following `num_leds` pixels, see
+    /// `Apa102Encoder::end_frame_len`
+    pub fn end_frame_len(&self, num_leds: usize) -> usize {
+        self.encoder.end_frame_len(num_leds)
+    }
+
+    /// Wrap `pixels` in a `Frame`, `None` if their number doesn't match
+    /// the one set with `Apa102Encoder::set_num_leds`
+    pub fn frame<'a>(&self, pixels: &'a mut [Apa102Pixel]) -> Option<Frame<'a>> {
+        Frame::new(pixels, self.encoder.num_leds()?)
+    }
+
+    /// Borrow the SPI, e.g. to change its frequency, without consuming self
+    pub fn bus_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
     /// Free the owned resources consuming self
     pub fn free(self) -> SPI {
         self.spi
     }
 }
 
+impl<SPI> Apa102<SPI>
+where
+    SPI: ClockedLedBus,
+{
+    /// Write an already encoded frame, e.g. produced by `Apa102Encoder`
+    ///
+    /// The bytes are sent as is, including start and end frame.
+    pub fn write_raw_frame(&mut self, frame: &[u8]) -> Result<(), SPI::Error> {
+        self.spi.write_bytes(frame)
+    }
+
+    /// Write pixels from a flat slice of RGB triplets, e.g. received from a
+    /// host
+    ///
+    /// A new pixel starts every `stride` bytes, with its first three bytes
+    /// being red, green and blue. All pixels get the same `brightness`.
+    /// Incomplete pixels at the end are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride` is smaller than 3.
+    pub fn write_rgb8_bytes(
+        &mut self,
+        bytes: &[u8],
+        stride: usize,
+        brightness: u8,
+    ) -> Result<(), SPI::Error> {
+        assert!(stride >= 3, "stride must be at least 3");
+        let pixels = bytes
+            .chunks(stride)
+            .filter(|pixel| pixel.len() >= 3)
+            .map(|pixel| Apa102Pixel::new(pixel[0], pixel[1], pixel[2], brightness));
+        self.write(pixels)
+    }
+
+    /// Write all the items of an iterator, using `brightness` instead of the
+    /// global brightness for this frame only
+    pub fn write_with_brightness<T, I>(
+        &mut self,
+        iterator: T,
+        brightness: u8,
+    ) -> Result<(), SPI::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Apa102Pixel>,
+    {
+        self.encoder.next_frame();
+        self.encoder
+            .write_frame(&mut self.spi, iterator, Some(brightness))
+    }
+
+    /// Write all the items of an iterator, checking they are as many as the
+    /// strip has leds
+    ///
+    /// Nothing is written if the length of the iterator doesn't match the one
+    /// set with `Apa102Encoder::set_num_leds`. Without it any length is
+    /// accepted.
+    pub fn write_exact<T, I>(&mut self, iterator: T) -> Result<(), Error<SPI::Error>>
+    where
+        T: IntoIterator<Item = I>,
+        T::IntoIter: ExactSizeIterator,
+        I: Into<Apa102Pixel>,
+    {
+        let iterator = iterator.into_iter();
+        if let Some(expected) = self.encoder.num_leds() {
+            if iterator.len() != expected {
+                let actual = iterator.len();
+                return Err(Error::WrongLength { expected, actual });
+            }
+        }
+        self.write(iterator).map_err(Error::Spi)
+    }
+
+    /// Set every led of the strip to `pixel`, e.g. an `RGB8` color
+    ///
+    /// Writes as many copies as set with `Apa102Encoder::set_num_leds`, and
+    /// fails with `Error::NumLedsUnset` without it.
+    pub fn fill(&mut self, pixel: impl Into<Apa102Pixel>) -> Result<(), Error<SPI::Error>> {
+        let num_leds = self.encoder.num_leds().ok_or(Error::NumLedsUnset)?;
+        self.write(repeat_n(pixel.into(), num_leds))
+            .map_err(Error::Spi)
+    }
+
+    /// Switch every led of the strip off
+    ///
+    /// Writes black pixels followed by the end frame, see `fill`.
+    pub fn clear(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.fill(Apa102Pixel::default())
+    }
+}
+
 impl<SPI> SmartLedsWrite for Apa102<SPI>
 where
-    SPI: SpiBus,
+    SPI: ClockedLedBus,
 {
-    type Color = RGB8;
+    type Color = Apa102Pixel;
     type Error = SPI::Error;
     /// Write all the items of an iterator to an apa102 strip
     fn write<T, I>(&mut self, iterator: T) -> Result<(), SPI::Error>
@@ -93,24 +469,7 @@ where
         T: IntoIterator<Item = I>,
         I: Into<Self::Color>,
     {
-        self.spi.write(&[0x00, 0x00, 0x00, 0x00])?;
-        for item in iterator {
-            let item = item.into();
-            match self.pixel_order {
-                PixelOrder::RGB => self.spi.write(&[0xFF, item.r, item.g, item.b])?,
-                PixelOrder::RBG => self.spi.write(&[0xFF, item.r, item.b, item.g])?,
-                PixelOrder::GRB => self.spi.write(&[0xFF, item.g, item.r, item.b])?,
-                PixelOrder::GBR => self.spi.write(&[0xFF, item.g, item.b, item.r])?,
-                PixelOrder::BRG => self.spi.write(&[0xFF, item.b, item.r, item.g])?,
-                PixelOrder::BGR => self.spi.write(&[0xFF, item.b, item.g, item.r])?,
-            }
-        }
-        for _ in 0..self.end_frame_length {
-            match self.invert_end_frame {
-                false => self.spi.write(&[0xFF])?,
-                true => self.spi.write(&[0x00])?,
-            };
-        }
-        Ok(())
+        self.encoder.next_frame();
+        self.encoder.write_frame(&mut self.spi, iterator, None)
     }
 }
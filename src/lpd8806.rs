@@ -0,0 +1,66 @@
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::chunked::Chunked;
+use crate::{ClockedLedBus, PixelOrder};
+
+/// A controller for a series of LPD8806 LEDs
+///
+/// LPD8806 leds only have 7 bits per channel, so the lowest bit of every
+/// color is dropped. The frame is latched by zero bytes following the pixel
+/// data, one for every 32 leds written.
+pub struct Lpd8806<SPI> {
+    spi: SPI,
+    pixel_order: PixelOrder,
+}
+
+impl<SPI> Lpd8806<SPI> {
+    /// new constructs a controller for a series of LPD8806 LEDs.
+    /// PixelOrder defaults to GRB, and can be customized using
+    /// new_with_options()
+    pub fn new(spi: SPI) -> Lpd8806<SPI> {
+        Self::new_with_options(spi, PixelOrder::GRB)
+    }
+
+    pub fn new_with_options(spi: SPI, pixel_order: PixelOrder) -> Lpd8806<SPI> {
+        Self { spi, pixel_order }
+    }
+
+    /// Borrow the SPI, e.g. to change its frequency, without consuming self
+    pub fn bus_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// Free the owned resources consuming self
+    pub fn free(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI> SmartLedsWrite for Lpd8806<SPI>
+where
+    SPI: ClockedLedBus,
+{
+    type Color = RGB8;
+    type Error = SPI::Error;
+    /// Write all the items of an iterator to a LPD8806 strip
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), SPI::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let mut chunked = Chunked::new(&mut self.spi);
+        let mut num_leds: usize = 0;
+        for item in iterator {
+            let item = item.into();
+            // The highest bit of every data byte has to be set
+            let pixel = self
+                .pixel_order
+                .order(item.r, item.g, item.b)
+                .map(|c| 0x80 | c >> 1);
+            chunked.push(&pixel)?;
+            num_leds += 1;
+        }
+        chunked.fill(0x00, num_leds.div_ceil(32))?;
+        chunked.finish()
+    }
+}
@@ -0,0 +1,200 @@
+//! Integer helpers shared by the color code
+
+use smart_leds_trait::RGB8;
+
+/// Scale `value` by `scale / 256`, with a `scale` of 255 keeping it unchanged
+pub fn scale8(value: u8, scale: u8) -> u8 {
+    ((value as u16 * (scale as u16 + 1)) >> 8) as u8
+}
+
+/// Like `scale8`, but never scales a non-zero value down to zero
+pub fn scale8_video(value: u8, scale: u8) -> u8 {
+    let scaled = ((value as u16 * scale as u16) >> 8) as u8;
+    if value != 0 && scale != 0 {
+        scaled + 1
+    } else {
+        scaled
+    }
+}
+
+/// Mix two values, `amount` going from 0 for `from` to 255 for `to`
+pub(crate) fn lerp8(from: u8, to: u8, amount: u8) -> u8 {
+    (from as i32 + (to as i32 - from as i32) * amount as i32 / 255) as u8
+}
+
+/// Mix two colors channel by channel, see `lerp8`
+pub(crate) fn lerp_rgb8(from: RGB8, to: RGB8, amount: u8) -> RGB8 {
+    RGB8::new(
+        lerp8(from.r, to.r, amount),
+        lerp8(from.g, to.g, amount),
+        lerp8(from.b, to.b, amount),
+    )
+}
+
+/// Solve `y^n = x` for a non-negative `x`, as `core` has no float math
+#[cfg(feature = "oklab")]
+pub(crate) fn root(x: f32, n: u32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    // Dividing the exponent bits gives a close enough first guess for
+    // Newton's method to converge in a few steps
+    let bits = (x.to_bits() as i32 - 0x3F80_0000) / n as i32 + 0x3F80_0000;
+    let mut y = f32::from_bits(bits as u32);
+    for _ in 0..5 {
+        let p = powi(y, n - 1);
+        y = ((n - 1) as f32 * y + x / p) / n as f32;
+    }
+    y
+}
+
+/// Raise `x` to the `n`th power
+#[cfg(feature = "oklab")]
+pub(crate) fn powi(x: f32, n: u32) -> f32 {
+    (0..n).fold(1.0, |acc, _| acc * x)
+}
+
+/// The sine of `x` radians
+#[cfg(feature = "oklab")]
+pub(crate) fn sin(x: f32) -> f32 {
+    use core::f32::consts::{FRAC_PI_2, PI, TAU};
+    // Fold into -π/2..=π/2, where the Taylor series up to x^11 is accurate
+    // to about 1e-7
+    let x = x % TAU;
+    let x = if x > PI {
+        x - TAU
+    } else if x < -PI {
+        x + TAU
+    } else {
+        x
+    };
+    let x = if x > FRAC_PI_2 {
+        PI - x
+    } else if x < -FRAC_PI_2 {
+        -PI - x
+    } else {
+        x
+    };
+    let x2 = x * x;
+    let mut term = x;
+    let mut sum = x;
+    for n in (2..12).step_by(2) {
+        term *= -x2 / (n * (n + 1)) as f32;
+        sum += term;
+    }
+    sum
+}
+
+/// The cosine of `x` radians
+#[cfg(feature = "oklab")]
+pub(crate) fn cos(x: f32) -> f32 {
+    sin(x + core::f32::consts::FRAC_PI_2)
+}
+
+/// The angle of the point `(x, y)` from the x axis, in radians in -π..=π
+#[cfg(feature = "oklab")]
+pub(crate) fn atan2(y: f32, x: f32) -> f32 {
+    use core::f32::consts::{FRAC_PI_2, PI};
+    if x == 0.0 && y == 0.0 {
+        return 0.0;
+    }
+    // atan of a ratio in -1..=1, a minimax polynomial accurate to about 1e-5
+    let atan = |z: f32| {
+        let z2 = z * z;
+        let p = -0.0117212 * z2 + 0.05265332;
+        let p = p * z2 - 0.11643287;
+        let p = p * z2 + 0.19354346;
+        let p = p * z2 - 0.33262347;
+        z * (p * z2 + 0.99997726)
+    };
+    if x.abs() >= y.abs() {
+        let angle = atan(y / x);
+        match (x < 0.0, y < 0.0) {
+            (false, _) => angle,
+            (true, false) => angle + PI,
+            (true, true) => angle - PI,
+        }
+    } else {
+        let angle = -atan(x / y);
+        match y < 0.0 {
+            false => angle + FRAC_PI_2,
+            true => angle - FRAC_PI_2,
+        }
+    }
+}
+
+/// Scale `value` by `scale / 65536`, the 16 bit version of `scale8`
+pub(crate) fn scale16(value: u16, scale: u16) -> u16 {
+    ((value as u32 * (scale as u32 + 1)) >> 16) as u16
+}
+
+/// Quadratic ease in and out over the whole range of `u8`
+pub fn ease8_in_out_quad(i: u8) -> u8 {
+    let j = if i & 0x80 != 0 { 255 - i } else { i };
+    let j = scale8(j, j) << 1;
+    if i & 0x80 != 0 {
+        255 - j
+    } else {
+        j
+    }
+}
+
+/// Quadratic ease in and out over the whole range of `u16`
+pub fn ease16_in_out_quad(i: u16) -> u16 {
+    let j = if i & 0x8000 != 0 { 65535 - i } else { i };
+    let j = scale16(j, j) << 1;
+    if i & 0x8000 != 0 {
+        65535 - j
+    } else {
+        j
+    }
+}
+
+/// `x` to the power of `y` for a positive `x`, usable in `const` contexts
+///
+/// Accurate to about 6 digits, plenty for building 8 bit tables.
+pub(crate) const fn powf(x: f32, y: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    exp2(y * log2(x))
+}
+
+const fn log2(x: f32) -> f32 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 23) & 0xFF) as i32 - 127;
+    let mantissa = f32::from_bits((bits & 0x007F_FFFF) | 0x3F80_0000);
+    // ln(m) = 2 atanh((m - 1) / (m + 1)), converging quickly for m in 1..2
+    let t = (mantissa - 1.0) / (mantissa + 1.0);
+    let t2 = t * t;
+    let mut term = t;
+    let mut ln = 0.0;
+    let mut n = 1;
+    while n < 16 {
+        ln += term / n as f32;
+        term *= t2;
+        n += 2;
+    }
+    exponent as f32 + 2.0 * ln / core::f32::consts::LN_2
+}
+
+const fn exp2(y: f32) -> f32 {
+    let mut whole = y as i32;
+    if whole as f32 > y {
+        whole -= 1;
+    }
+    if whole < -126 {
+        return 0.0;
+    }
+    // Taylor series of e^(f ln 2) for the fractional part f in 0..1
+    let x = (y - whole as f32) * core::f32::consts::LN_2;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut n = 1;
+    while n < 10 {
+        term *= x / n as f32;
+        sum += term;
+        n += 1;
+    }
+    sum * f32::from_bits(((whole + 127) as u32) << 23)
+}
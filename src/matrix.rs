@@ -0,0 +1,63 @@
+use crate::{Apa102, Apa102Buffered, Apa102Pixel, ClockedLedBus, MatrixMap};
+
+/// A controller owning a framebuffer for a matrix of up to `N` APA102 LEDs
+///
+/// Pixels are set by their coordinates using `set_xy`, translated to the
+/// strip by a `MatrixMap`, and transmitted all at once by calling `flush()`.
+pub struct Apa102Matrix<SPI, const N: usize> {
+    buffered: Apa102Buffered<SPI, N>,
+    map: MatrixMap,
+}
+
+impl<SPI, const N: usize> Apa102Matrix<SPI, N>
+where
+    SPI: ClockedLedBus,
+{
+    /// Wraps an existing controller, starting with all pixels turned off
+    ///
+    /// # Panics
+    ///
+    /// Panics if the matrix has more than `N` leds.
+    pub fn new(apa102: Apa102<SPI>, map: MatrixMap) -> Apa102Matrix<SPI, N> {
+        assert!(map.len() <= N, "matrix has more than N leds");
+        Self {
+            buffered: Apa102Buffered::new(apa102),
+            map,
+        }
+    }
+
+    /// Set the pixel at `(x, y)`, from an `Apa102Pixel` or a plain `RGB8`
+    ///
+    /// Pixels outside of the matrix are ignored. The change only shows up on
+    /// the strip after the next `flush()`.
+    pub fn set_xy(&mut self, x: usize, y: usize, pixel: impl Into<Apa102Pixel>) {
+        if let Some(index) = self.map.index(x, y) {
+            self.buffered.set_pixel(index, pixel);
+        }
+    }
+
+    /// Write the whole framebuffer to the strip
+    pub fn flush(&mut self) -> Result<(), SPI::Error> {
+        self.buffered.flush()
+    }
+
+    /// Write the framebuffer only if it changed, see
+    /// `Apa102Buffered::flush_if_changed`
+    pub fn flush_if_changed(&mut self) -> Result<bool, SPI::Error> {
+        self.buffered.flush_if_changed()
+    }
+
+    pub fn map(&self) -> &MatrixMap {
+        &self.map
+    }
+
+    /// Borrow the framebuffer, e.g. to change its settings or SPI
+    pub fn buffered_mut(&mut self) -> &mut Apa102Buffered<SPI, N> {
+        &mut self.buffered
+    }
+
+    /// Free the owned framebuffer consuming self
+    pub fn free(self) -> Apa102Buffered<SPI, N> {
+        self.buffered
+    }
+}
@@ -0,0 +1,289 @@
+//! Integer Perlin noise, ported from FastLED so effects using `inoise8` and
+//! `inoise16` produce the same patterns
+
+use crate::math::{ease16_in_out_quad, ease8_in_out_quad, scale16, scale8};
+
+/// Ken Perlin's permutation, with the first entry repeated so that `P(x + 1)`
+/// doesn't need to wrap
+const PERMUTATION: [u8; 257] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180, 151,
+];
+
+fn p(i: u8) -> u8 {
+    PERMUTATION[i as usize]
+}
+
+fn p1(i: u8) -> u8 {
+    PERMUTATION[i as usize + 1]
+}
+
+/// 1D noise with 16 bits of precision
+///
+/// The integer part of the coordinate is in the upper 16 bits, so it should
+/// be advanced in steps well below 65536 for smooth changes.
+pub fn inoise16(x: u32) -> u16 {
+    let x_cell = (x >> 16) as u8;
+    let a = p(p(x_cell));
+    let b = p(p1(x_cell));
+
+    let (xx, nx) = split16(x);
+    let u = ease16_in_out_quad(x as u16);
+
+    let ans = lerp15by16(grad16_1d(p(a), xx), grad16_1d(p(b), nx), u);
+    ((ans as i32 + 17308) as u32).wrapping_shl(1) as u16
+}
+
+/// 2D noise with 16 bits of precision, see `inoise16`
+pub fn inoise16_2d(x: u32, y: u32) -> u16 {
+    let (x_cell, y_cell) = ((x >> 16) as u8, (y >> 16) as u8);
+    let a = p(x_cell).wrapping_add(y_cell);
+    let b = p1(x_cell).wrapping_add(y_cell);
+    let (aa, ab, ba, bb) = (p(a), p1(a), p(b), p1(b));
+
+    let (xx, nx) = split16(x);
+    let (yy, ny) = split16(y);
+    let u = ease16_in_out_quad(x as u16);
+    let v = ease16_in_out_quad(y as u16);
+
+    let x1 = lerp15by16(grad16_2d(p(aa), xx, yy), grad16_2d(p(ba), nx, yy), u);
+    let x2 = lerp15by16(grad16_2d(p(ab), xx, ny), grad16_2d(p(bb), nx, ny), u);
+    let ans = lerp15by16(x1, x2, v);
+    ((ans as i32 + 17308) as u32)
+        .wrapping_mul(484)
+        .wrapping_shr(8) as u16
+}
+
+/// 3D noise with 16 bits of precision, see `inoise16`
+///
+/// A common use is a 2D pattern with time as the third coordinate.
+pub fn inoise16_3d(x: u32, y: u32, z: u32) -> u16 {
+    let (x_cell, y_cell, z_cell) = ((x >> 16) as u8, (y >> 16) as u8, (z >> 16) as u8);
+    let a = p(x_cell).wrapping_add(y_cell);
+    let b = p1(x_cell).wrapping_add(y_cell);
+    let aa = p(a).wrapping_add(z_cell);
+    let ab = p1(a).wrapping_add(z_cell);
+    let ba = p(b).wrapping_add(z_cell);
+    let bb = p1(b).wrapping_add(z_cell);
+
+    let (xx, nx) = split16(x);
+    let (yy, ny) = split16(y);
+    let (zz, nz) = split16(z);
+    let u = ease16_in_out_quad(x as u16);
+    let v = ease16_in_out_quad(y as u16);
+    let w = ease16_in_out_quad(z as u16);
+
+    let x1 = lerp15by16(
+        grad16_3d(p(aa), xx, yy, zz),
+        grad16_3d(p(ba), nx, yy, zz),
+        u,
+    );
+    let x2 = lerp15by16(
+        grad16_3d(p(ab), xx, ny, zz),
+        grad16_3d(p(bb), nx, ny, zz),
+        u,
+    );
+    let x3 = lerp15by16(
+        grad16_3d(p1(aa), xx, yy, nz),
+        grad16_3d(p1(ba), nx, yy, nz),
+        u,
+    );
+    let x4 = lerp15by16(
+        grad16_3d(p1(ab), xx, ny, nz),
+        grad16_3d(p1(bb), nx, ny, nz),
+        u,
+    );
+    let ans = lerp15by16(lerp15by16(x1, x2, v), lerp15by16(x3, x4, v), w);
+    ((ans as i32 + 19052) as u32)
+        .wrapping_mul(440)
+        .wrapping_shr(8) as u16
+}
+
+/// 1D noise with 8 bits of precision
+///
+/// The integer part of the coordinate is in the upper 8 bits, so it should
+/// be advanced in steps well below 256 for smooth changes.
+pub fn inoise8(x: u16) -> u8 {
+    let x_cell = (x >> 8) as u8;
+    let a = p(p(x_cell));
+    let b = p(p1(x_cell));
+
+    let (xx, nx) = split8(x);
+    let u = ease8_in_out_quad(x as u8);
+
+    to_u8(lerp7by8(grad8_1d(p(a), xx), grad8_1d(p(b), nx), u))
+}
+
+/// 2D noise with 8 bits of precision, see `inoise8`
+pub fn inoise8_2d(x: u16, y: u16) -> u8 {
+    let (x_cell, y_cell) = ((x >> 8) as u8, (y >> 8) as u8);
+    let a = p(x_cell).wrapping_add(y_cell);
+    let b = p1(x_cell).wrapping_add(y_cell);
+    let (aa, ab, ba, bb) = (p(a), p1(a), p(b), p1(b));
+
+    let (xx, nx) = split8(x);
+    let (yy, ny) = split8(y);
+    let u = ease8_in_out_quad(x as u8);
+    let v = ease8_in_out_quad(y as u8);
+
+    let x1 = lerp7by8(grad8_2d(p(aa), xx, yy), grad8_2d(p(ba), nx, yy), u);
+    let x2 = lerp7by8(grad8_2d(p(ab), xx, ny), grad8_2d(p(bb), nx, ny), u);
+    to_u8(lerp7by8(x1, x2, v))
+}
+
+/// 3D noise with 8 bits of precision, see `inoise8`
+pub fn inoise8_3d(x: u16, y: u16, z: u16) -> u8 {
+    let (x_cell, y_cell, z_cell) = ((x >> 8) as u8, (y >> 8) as u8, (z >> 8) as u8);
+    let a = p(x_cell).wrapping_add(y_cell);
+    let b = p1(x_cell).wrapping_add(y_cell);
+    let aa = p(a).wrapping_add(z_cell);
+    let ab = p1(a).wrapping_add(z_cell);
+    let ba = p(b).wrapping_add(z_cell);
+    let bb = p1(b).wrapping_add(z_cell);
+
+    let (xx, nx) = split8(x);
+    let (yy, ny) = split8(y);
+    let (zz, nz) = split8(z);
+    let u = ease8_in_out_quad(x as u8);
+    let v = ease8_in_out_quad(y as u8);
+    let w = ease8_in_out_quad(z as u8);
+
+    let x1 = lerp7by8(grad8_3d(p(aa), xx, yy, zz), grad8_3d(p(ba), nx, yy, zz), u);
+    let x2 = lerp7by8(grad8_3d(p(ab), xx, ny, zz), grad8_3d(p(bb), nx, ny, zz), u);
+    let x3 = lerp7by8(
+        grad8_3d(p1(aa), xx, yy, nz),
+        grad8_3d(p1(ba), nx, yy, nz),
+        u,
+    );
+    let x4 = lerp7by8(
+        grad8_3d(p1(ab), xx, ny, nz),
+        grad8_3d(p1(bb), nx, ny, nz),
+        u,
+    );
+    to_u8(lerp7by8(lerp7by8(x1, x2, v), lerp7by8(x3, x4, v), w))
+}
+
+/// Signed distances of the point to both sides of its cell
+fn split16(coordinate: u32) -> (i16, i16) {
+    let near = (coordinate as u16 >> 1) as i16;
+    (near, (near as i32 - 0x8000) as i16)
+}
+
+fn split8(coordinate: u16) -> (i8, i8) {
+    let near = (coordinate as u8 >> 1) as i8;
+    (near, (near as i16 - 0x80) as i8)
+}
+
+/// Map the raw -64..=64 noise to the whole range of `u8`
+fn to_u8(raw: i8) -> u8 {
+    let n = (raw as i16 + 64) as u8;
+    n.saturating_add(n)
+}
+
+fn lerp15by16(a: i16, b: i16, frac: u16) -> i16 {
+    if b > a {
+        let scaled = scale16((b as i32 - a as i32) as u16, frac);
+        a.wrapping_add(scaled as i16)
+    } else {
+        let scaled = scale16((a as i32 - b as i32) as u16, frac);
+        a.wrapping_sub(scaled as i16)
+    }
+}
+
+fn lerp7by8(a: i8, b: i8, frac: u8) -> i8 {
+    if b > a {
+        let scaled = scale8((b as i16 - a as i16) as u8, frac);
+        a.wrapping_add(scaled as i8)
+    } else {
+        let scaled = scale8((a as i16 - b as i16) as u8, frac);
+        a.wrapping_sub(scaled as i8)
+    }
+}
+
+fn avg15(i: i16, j: i16) -> i16 {
+    (i >> 1) + (j >> 1) + (i & 1)
+}
+
+fn avg7(i: i8, j: i8) -> i8 {
+    (i >> 1) + (j >> 1) + (i & 1)
+}
+
+/// Negate `u` and `v` depending on the lowest two bits of `hash`
+fn signs16(hash: u8, u: i16, v: i16) -> i16 {
+    let u = if hash & 1 != 0 { u.wrapping_neg() } else { u };
+    let v = if hash & 2 != 0 { v.wrapping_neg() } else { v };
+    avg15(u, v)
+}
+
+fn signs8(hash: u8, u: i8, v: i8) -> i8 {
+    let u = if hash & 1 != 0 { u.wrapping_neg() } else { u };
+    let v = if hash & 2 != 0 { v.wrapping_neg() } else { v };
+    avg7(u, v)
+}
+
+fn grad16_1d(hash: u8, x: i16) -> i16 {
+    let hash = hash & 15;
+    let (u, v) = if hash > 8 {
+        (x, x)
+    } else if hash < 4 {
+        (x, 1)
+    } else {
+        (1, x)
+    };
+    signs16(hash, u, v)
+}
+
+fn grad16_2d(hash: u8, x: i16, y: i16) -> i16 {
+    let hash = hash & 7;
+    let (u, v) = if hash < 4 { (x, y) } else { (y, x) };
+    signs16(hash, u, v)
+}
+
+fn grad16_3d(hash: u8, x: i16, y: i16, z: i16) -> i16 {
+    let hash = hash & 15;
+    let u = if hash < 8 { x } else { y };
+    let v = match hash {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    signs16(hash, u, v)
+}
+
+fn grad8_1d(hash: u8, x: i8) -> i8 {
+    let (u, v) = if hash & 8 != 0 {
+        (x, x)
+    } else if hash & 4 != 0 {
+        (1, x)
+    } else {
+        (x, 1)
+    };
+    signs8(hash, u, v)
+}
+
+fn grad8_2d(hash: u8, x: i8, y: i8) -> i8 {
+    let (u, v) = if hash & 4 != 0 { (y, x) } else { (x, y) };
+    signs8(hash, u, v)
+}
+
+fn grad8_3d(hash: u8, x: i8, y: i8, z: i8) -> i8 {
+    let hash = hash & 15;
+    let u = if hash & 8 != 0 { y } else { x };
+    let v = match hash {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    signs8(hash, u, v)
+}
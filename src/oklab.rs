@@ -0,0 +1,220 @@
+use core::f32::consts::PI;
+
+use smart_leds_trait::RGB8;
+
+use crate::gamma::srgb_to_linear;
+use crate::math::{atan2, cos, powi, root, sin};
+use crate::Apa102Pixel;
+
+/// A color in the OKLab color space
+///
+/// Colors mixed in OKLab keep their perceived lightness and saturation, so
+/// gradients don't get the dark or greyish midpoints of mixing RGB values.
+///
+/// Conversions from `RGB8` treat the channels as sRGB, e.g. colors picked on
+/// a screen. Conversions to `RGB8` and `Apa102Pixel` give linear light, as
+/// leds take it, so they are meant to be written without a gamma curve set
+/// with `Apa102Encoder::set_gamma`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+impl Oklab {
+    pub const fn new(l: f32, a: f32, b: f32) -> Self {
+        Self { l, a, b }
+    }
+
+    /// Mix two colors, `amount` going from 0 for `self` to 255 for `other`
+    pub fn lerp(self, other: Oklab, amount: u8) -> Oklab {
+        let mix = |from, to| mix(from, to, amount);
+        Oklab::new(
+            mix(self.l, other.l),
+            mix(self.a, other.a),
+            mix(self.b, other.b),
+        )
+    }
+}
+
+impl From<RGB8> for Oklab {
+    fn from(color: RGB8) -> Self {
+        let r = srgb_to_linear(color.r as f32 / 255.0);
+        let g = srgb_to_linear(color.g as f32 / 255.0);
+        let b = srgb_to_linear(color.b as f32 / 255.0);
+
+        let l = root(0.41222147 * r + 0.53633254 * g + 0.051445993 * b, 3);
+        let m = root(0.2119035 * r + 0.6806995 * g + 0.10739696 * b, 3);
+        let s = root(0.08830246 * r + 0.28171884 * g + 0.6299787 * b, 3);
+
+        Oklab::new(
+            0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+            1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+            0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+        )
+    }
+}
+
+impl From<Oklab> for RGB8 {
+    /// Convert to linear light, clamping colors outside of the sRGB gamut
+    fn from(color: Oklab) -> Self {
+        let l = powi(color.l + 0.39633778 * color.a + 0.21580376 * color.b, 3);
+        let m = powi(color.l - 0.105561346 * color.a - 0.06385417 * color.b, 3);
+        let s = powi(color.l - 0.08948418 * color.a - 1.2914855 * color.b, 3);
+
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        RGB8::new(
+            channel(4.0767417 * l - 3.3077116 * m + 0.23096994 * s),
+            channel(-1.268438 * l + 2.6097574 * m - 0.34131938 * s),
+            channel(-0.0041960864 * l - 0.7034186 * m + 1.7076147 * s),
+        )
+    }
+}
+
+impl From<Oklab> for Apa102Pixel {
+    fn from(color: Oklab) -> Self {
+        RGB8::from(color).into()
+    }
+}
+
+/// A color in OKLCh, the polar form of OKLab
+///
+/// Instead of the `a` and `b` axes, a color has a chroma `c` and a hue `h`
+/// in degrees. Mixing colors in OKLCh goes around the hue circle, so a
+/// gradient between two saturated colors stays saturated, e.g. red to blue
+/// passes through purple. Conversions are the same as for `Oklab`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Oklch {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+}
+
+impl Oklch {
+    pub const fn new(l: f32, c: f32, h: f32) -> Self {
+        Self { l, c, h }
+    }
+
+    /// Mix two colors, `amount` going from 0 for `self` to 255 for `other`
+    ///
+    /// The hue takes the shorter way around the circle. A gray has no hue,
+    /// so mixing it with a color keeps the hue of that color.
+    pub fn lerp(self, other: Oklch, amount: u8) -> Oklch {
+        let (from, to) = match (self.c > 0.0, other.c > 0.0) {
+            (false, true) => (other.h, other.h),
+            (true, false) => (self.h, self.h),
+            _ => (self.h, self.h + degrees(other.h - self.h + 180.0) - 180.0),
+        };
+        Oklch::new(
+            mix(self.l, other.l, amount),
+            mix(self.c, other.c, amount),
+            degrees(mix(from, to, amount)),
+        )
+    }
+}
+
+impl From<Oklab> for Oklch {
+    fn from(color: Oklab) -> Self {
+        let c = root(color.a * color.a + color.b * color.b, 2);
+        let h = atan2(color.b, color.a) * 180.0 / PI;
+        Oklch::new(color.l, c, degrees(h))
+    }
+}
+
+impl From<Oklch> for Oklab {
+    fn from(color: Oklch) -> Self {
+        let h = color.h * PI / 180.0;
+        Oklab::new(color.l, color.c * cos(h), color.c * sin(h))
+    }
+}
+
+impl From<RGB8> for Oklch {
+    fn from(color: RGB8) -> Self {
+        Oklab::from(color).into()
+    }
+}
+
+impl From<Oklch> for RGB8 {
+    /// Convert to linear light, see `Oklab`
+    fn from(color: Oklch) -> Self {
+        Oklab::from(color).into()
+    }
+}
+
+impl From<Oklch> for Apa102Pixel {
+    fn from(color: Oklch) -> Self {
+        RGB8::from(color).into()
+    }
+}
+
+/// Mix two sRGB colors in OKLab, `amount` going from 0 for `from` to 255
+/// for `to`
+///
+/// The result is linear light, see `Oklab`.
+pub fn lerp_oklab(from: RGB8, to: RGB8, amount: u8) -> Apa102Pixel {
+    Oklab::from(from).lerp(Oklab::from(to), amount).into()
+}
+
+/// Mix two sRGB colors in OKLCh, around the hue circle, see `lerp_oklab`
+pub fn lerp_oklch(from: RGB8, to: RGB8, amount: u8) -> Apa102Pixel {
+    Oklch::from(from).lerp(Oklch::from(to), amount).into()
+}
+
+/// Wrap an angle into 0.0..360.0 degrees
+fn degrees(angle: f32) -> f32 {
+    let angle = angle % 360.0;
+    if angle < 0.0 {
+        angle + 360.0
+    } else {
+        angle
+    }
+}
+
+/// Mix two values, `amount` going from 0 for `from` to 255 for `to`
+fn mix(from: f32, to: f32, amount: u8) -> f32 {
+    from + (to - from) * (amount as f32 / 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: RGB8 = RGB8::new(255, 0, 0);
+    const BLUE: RGB8 = RGB8::new(0, 0, 255);
+
+    #[test]
+    fn converts_srgb_to_linear_light() {
+        let gray = Oklab::from(RGB8::new(128, 128, 128));
+        assert_eq!(RGB8::from(gray), RGB8::new(55, 55, 55));
+        assert_eq!(lerp_oklab(RED, BLUE, 0).color, RED);
+        assert_eq!(lerp_oklab(RED, BLUE, 255).color, BLUE);
+    }
+
+    #[test]
+    fn converts_between_oklab_and_oklch() {
+        for color in [RED, BLUE, RGB8::new(12, 200, 90)] {
+            let oklch = Oklch::from(color);
+            assert_eq!(RGB8::from(oklch), RGB8::from(Oklab::from(color)));
+        }
+        let oklch = Oklch::from(Oklab::new(0.5, 0.0, -0.1));
+        assert!((oklch.c - 0.1).abs() < 1e-4);
+        assert!((oklch.h - 270.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn mixes_hue_the_short_way_around() {
+        let from = Oklch::new(0.5, 0.1, 350.0);
+        let mixed = from.lerp(Oklch::new(0.5, 0.1, 30.0), 128);
+        assert!((mixed.h - 10.0).abs() < 0.2);
+        let gray = Oklch::new(0.5, 0.0, 0.0);
+        assert_eq!(gray.lerp(from, 128).h, 350.0);
+    }
+
+    #[test]
+    fn keeps_chroma_between_saturated_colors() {
+        let oklab = Oklch::from(Oklab::from(RED).lerp(Oklab::from(BLUE), 128));
+        let oklch = Oklch::from(RED).lerp(Oklch::from(BLUE), 128);
+        assert!(oklch.c > oklab.c + 0.05);
+    }
+}
@@ -0,0 +1,244 @@
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::pixel::set_channel;
+use crate::Apa102Pixel;
+
+const SET_PIXEL_COLORS: u8 = 0;
+
+/// Decodes Open Pixel Control messages, e.g. received over TCP
+///
+/// Every message starts with a channel, a command and the data length as big
+/// endian `u16`. Set pixel colors messages hold the pixels as RGB triplets
+/// and are decoded into a buffer of `N` pixels, leds beyond that are
+/// dropped. Other commands are skipped.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, ClockedLedBus, OpcDecoder};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let mut strips = [Apa102::new(Spi), Apa102::new(Spi)];
+/// # let received = [0; 64];
+/// let mut decoder: OpcDecoder<100> = OpcDecoder::new();
+/// for byte in received {
+///     if let Some(frame) = decoder.push(byte) {
+///         // Channel 1 on strips[0], channel 2 on strips[1]
+///         frame.route(&mut strips)?;
+///     }
+/// }
+/// # Ok::<(), ()>(())
+/// ```
+pub struct OpcDecoder<const N: usize> {
+    state: State,
+    pixels: [RGB8; N],
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    /// Header bytes and the number received so far
+    Header([u8; 4], usize),
+    /// Channel, command, data length and number of data bytes received
+    Data(u8, u8, usize, usize),
+}
+
+/// A decoded set pixel colors message
+#[derive(Clone, Copy, Debug)]
+pub struct OpcFrame<'a> {
+    /// The channel the message is meant for, 0 meaning all channels
+    pub channel: u8,
+    pixels: &'a [RGB8],
+}
+
+impl<const N: usize> OpcDecoder<N> {
+    pub fn new() -> OpcDecoder<N> {
+        Self {
+            state: State::Header([0; 4], 0),
+            pixels: [RGB8::default(); N],
+        }
+    }
+
+    /// Feed the next received byte, returning a frame once a set pixel colors
+    /// message is complete
+    pub fn push(&mut self, byte: u8) -> Option<OpcFrame<'_>> {
+        let (channel, command, len) = match self.state {
+            State::Header(mut header, received) => {
+                header[received] = byte;
+                if received + 1 < header.len() {
+                    self.state = State::Header(header, received + 1);
+                    return None;
+                }
+                let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+                if len > 0 {
+                    self.state = State::Data(header[0], header[1], len, 0);
+                    return None;
+                }
+                (header[0], header[1], 0)
+            }
+            State::Data(channel, command, len, received) => {
+                if command == SET_PIXEL_COLORS {
+                    set_channel(&mut self.pixels, received, byte);
+                }
+                if received + 1 < len {
+                    self.state = State::Data(channel, command, len, received + 1);
+                    return None;
+                }
+                (channel, command, len)
+            }
+        };
+        self.state = State::Header([0; 4], 0);
+        (command == SET_PIXEL_COLORS).then(|| OpcFrame {
+            channel,
+            pixels: &self.pixels[..(len / 3).min(N)],
+        })
+    }
+}
+
+impl<const N: usize> Default for OpcDecoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> OpcFrame<'a> {
+    /// Iterate over the pixels of the frame
+    pub fn pixels(&self) -> impl Iterator<Item = Apa102Pixel> + 'a {
+        self.pixels.iter().map(|&c| c.into())
+    }
+
+    /// Whether the frame should be shown on the strip on `channel`
+    pub fn is_for(&self, channel: u8) -> bool {
+        self.channel == 0 || self.channel == channel
+    }
+
+    /// Write the frame to the strips it is meant for, the strip on channel 1
+    /// being the first of `writers`
+    pub fn route<W>(&self, writers: &mut [W]) -> Result<(), W::Error>
+    where
+        W: SmartLedsWrite,
+        Apa102Pixel: Into<W::Color>,
+    {
+        for (channel, writer) in (1..=u8::MAX).zip(writers.iter_mut()) {
+            if self.is_for(channel) {
+                writer.write(self.pixels())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::convert::Infallible;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::{colors, feed};
+
+    fn message(channel: u8, command: u8, data: &[u8]) -> Vec<u8> {
+        let mut message = [channel, command].to_vec();
+        message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        message.extend_from_slice(data);
+        message
+    }
+
+    /// Records the colors of the last write
+    #[derive(Default)]
+    struct Recorder(Vec<RGB8>);
+
+    impl SmartLedsWrite for Recorder {
+        type Color = Apa102Pixel;
+        type Error = Infallible;
+        fn write<T, I>(&mut self, iterator: T) -> Result<(), Infallible>
+        where
+            T: IntoIterator<Item = I>,
+            I: Into<Apa102Pixel>,
+        {
+            self.0 = iterator
+                .into_iter()
+                .map(|pixel| pixel.into().color)
+                .collect();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn decodes_set_pixel_colors() {
+        let mut decoder: OpcDecoder<4> = OpcDecoder::new();
+        let bytes = message(2, SET_PIXEL_COLORS, &[1, 2, 3, 4, 5, 6]);
+        let frames = feed(&bytes, |byte| {
+            let frame = decoder.push(byte)?;
+            Some((frame.channel, colors(frame.pixels())))
+        });
+        assert_eq!(
+            frames,
+            [(2, [RGB8::new(1, 2, 3), RGB8::new(4, 5, 6)].to_vec())]
+        );
+    }
+
+    #[test]
+    fn drops_leds_beyond_buffer_and_partial_pixels() {
+        let mut decoder: OpcDecoder<1> = OpcDecoder::new();
+        let mut bytes = message(1, SET_PIXEL_COLORS, &[1, 2, 3, 4, 5, 6]);
+        bytes.extend(message(1, SET_PIXEL_COLORS, &[7, 8, 9, 10]));
+        let frames = feed(&bytes, |byte| {
+            let frame = decoder.push(byte)?;
+            Some((frame.channel, colors(frame.pixels())))
+        });
+        assert_eq!(
+            frames,
+            [
+                (1, [RGB8::new(1, 2, 3)].to_vec()),
+                (1, [RGB8::new(7, 8, 9)].to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_other_commands() {
+        let mut decoder: OpcDecoder<4> = OpcDecoder::new();
+        let mut bytes = message(0, 0xFF, &[0x00, 0x01, 0x00, 0x01, 1, 2, 3]);
+        bytes.extend(message(0, SET_PIXEL_COLORS, &[]));
+        bytes.extend(message(0, SET_PIXEL_COLORS, &[4, 5, 6]));
+        let frames = feed(&bytes, |byte| {
+            let frame = decoder.push(byte)?;
+            Some((frame.channel, colors(frame.pixels())))
+        });
+        assert_eq!(
+            frames,
+            [(0, [].to_vec()), (0, [RGB8::new(4, 5, 6)].to_vec())]
+        );
+    }
+
+    /// Send a message filled with `color` on `channel` and route it
+    fn send<const N: usize>(
+        decoder: &mut OpcDecoder<N>,
+        strips: &mut [Recorder],
+        channel: u8,
+        color: u8,
+    ) {
+        for byte in message(channel, SET_PIXEL_COLORS, &[color; 3]) {
+            if let Some(frame) = decoder.push(byte) {
+                frame.route(strips).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn routes_by_channel() {
+        let mut decoder: OpcDecoder<4> = OpcDecoder::new();
+        let mut strips: [Recorder; 2] = Default::default();
+        send(&mut decoder, &mut strips, 0, 1);
+        send(&mut decoder, &mut strips, 3, 2);
+        assert_eq!(strips[0].0, [RGB8::new(1, 1, 1)]);
+        assert_eq!(strips[1].0, [RGB8::new(1, 1, 1)]);
+        send(&mut decoder, &mut strips, 2, 3);
+        assert_eq!(strips[0].0, [RGB8::new(1, 1, 1)]);
+        assert_eq!(strips[1].0, [RGB8::new(3, 3, 3)]);
+    }
+}
@@ -0,0 +1,88 @@
+use smart_leds_trait::RGB8;
+
+use crate::math::scale8;
+use crate::Apa102Pixel;
+
+/// A palette of 16 colors, indexed with `color_from_palette`
+///
+/// The predefined palettes match the ones shipped with FastLED, so sketches
+/// using them can be ported unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Palette16(pub [RGB8; 16]);
+
+impl Palette16 {
+    pub const CLOUD: Palette16 = Palette16::from_packed([
+        0x0000FF, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x00008B, 0x0000FF,
+        0x00008B, 0x87CEEB, 0x87CEEB, 0xADD8E6, 0xFFFFFF, 0xADD8E6, 0x87CEEB,
+    ]);
+    pub const LAVA: Palette16 = Palette16::from_packed([
+        0x000000, 0x800000, 0x000000, 0x800000, 0x8B0000, 0x8B0000, 0x800000, 0x8B0000, 0x8B0000,
+        0x8B0000, 0xFF0000, 0xFFA500, 0xFFFFFF, 0xFFA500, 0xFF0000, 0x8B0000,
+    ]);
+    pub const OCEAN: Palette16 = Palette16::from_packed([
+        0x191970, 0x00008B, 0x191970, 0x000080, 0x00008B, 0x0000CD, 0x2E8B57, 0x008080, 0x5F9EA0,
+        0x0000FF, 0x008B8B, 0x6495ED, 0x7FFFD4, 0x2E8B57, 0x00FFFF, 0x87CEFA,
+    ]);
+    pub const FOREST: Palette16 = Palette16::from_packed([
+        0x006400, 0x006400, 0x556B2F, 0x006400, 0x008000, 0x228B22, 0x6B8E23, 0x008000, 0x2E8B57,
+        0x66CDAA, 0x32CD32, 0x9ACD32, 0x90EE90, 0x7CFC00, 0x66CDAA, 0x228B22,
+    ]);
+    pub const RAINBOW: Palette16 = Palette16::from_packed([
+        0xFF0000, 0xD52A00, 0xAB5500, 0xAB7F00, 0xABAB00, 0x56D500, 0x00FF00, 0x00D52A, 0x00AB55,
+        0x0056AA, 0x0000FF, 0x2A00D5, 0x5500AB, 0x7F0081, 0xAB0055, 0xD5002B,
+    ]);
+    pub const RAINBOW_STRIPE: Palette16 = Palette16::from_packed([
+        0xFF0000, 0x000000, 0xAB5500, 0x000000, 0xABAB00, 0x000000, 0x00FF00, 0x000000, 0x00AB55,
+        0x000000, 0x0000FF, 0x000000, 0x5500AB, 0x000000, 0xAB0055, 0x000000,
+    ]);
+    pub const PARTY: Palette16 = Palette16::from_packed([
+        0x5500AB, 0x84007C, 0xB5004B, 0xE5001B, 0xE81700, 0xB84700, 0xAB7700, 0xABAB00, 0xAB5500,
+        0xDD2200, 0xF2000E, 0xC2003E, 0x8F0071, 0x5F00A1, 0x2F00D0, 0x0007F9,
+    ]);
+    pub const HEAT: Palette16 = Palette16::from_packed([
+        0x000000, 0x330000, 0x660000, 0x990000, 0xCC0000, 0xFF0000, 0xFF3300, 0xFF6600, 0xFF9900,
+        0xFFCC00, 0xFFFF00, 0xFFFF33, 0xFFFF66, 0xFFFF99, 0xFFFFCC, 0xFFFFFF,
+    ]);
+
+    /// Create a palette from packed `0xRRGGBB` colors
+    pub const fn from_packed(colors: [u32; 16]) -> Palette16 {
+        let mut entries = [RGB8 { r: 0, g: 0, b: 0 }; 16];
+        let mut i = 0;
+        while i < 16 {
+            let [_, r, g, b] = colors[i].to_be_bytes();
+            entries[i] = RGB8 { r, g, b };
+            i += 1;
+        }
+        Palette16(entries)
+    }
+}
+
+/// Look up the color at `index` in `palette`, matching `ColorFromPalette` of
+/// FastLED
+///
+/// Every entry covers 16 consecutive indices. With `blend` the colors are
+/// mixed with the following entry, wrapping around from the last entry to
+/// the first. `brightness` is passed on as the brightness of the pixel.
+pub fn color_from_palette(
+    palette: &Palette16,
+    index: u8,
+    brightness: u8,
+    blend: bool,
+) -> Apa102Pixel {
+    let entry = index as usize >> 4;
+    let color = palette.0[entry];
+    let next_amount = (index & 0x0F) << 4;
+    let color = if blend && next_amount != 0 {
+        let next = palette.0[(entry + 1) % 16];
+        let amount = 255 - next_amount;
+        let mix = |a: u8, b: u8| scale8(a, amount) + scale8(b, next_amount);
+        RGB8::new(
+            mix(color.r, next.r),
+            mix(color.g, next.g),
+            mix(color.b, next.b),
+        )
+    } else {
+        color
+    };
+    (color, brightness).into()
+}
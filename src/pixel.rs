@@ -0,0 +1,236 @@
+use smart_leds_trait::{RGB16, RGB8};
+
+use crate::colorutils::blend;
+use crate::conversion::five_bit;
+use crate::math::{powf, scale8};
+use crate::PixelOrder;
+
+/// A color with its own brightness, as written by `Apa102`
+///
+/// The brightness is combined with the global brightness of the controller
+/// and applied using its `BrightnessStrategy`. Plain `RGB8` colors convert
+/// to pixels at full brightness, and `(RGB8, u8)` tuples to pixels with the
+/// given brightness. Packed `0xRRGGBB` colors convert from `u32`, so e.g.
+/// `write(palette.iter().copied())` works on a `&[u32]` palette. `RGB16`
+/// colors convert as well, for high precision renderers.
+///
+/// `(RGB8, U5)` tuples convert to pixels sending the given 5 bit value as is
+/// instead, see `five_bit`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Apa102Pixel {
+    pub color: RGB8,
+    pub brightness: u8,
+    /// Sent in the 5 bit brightness field as is if set, ignoring the
+    /// `BrightnessStrategy` and the global current
+    ///
+    /// The brightness, combined with the global one, then only scales the
+    /// colors. Pixels mixed from others, e.g. by `lerp`, don't keep it.
+    pub five_bit: Option<U5>,
+}
+
+/// A value of the 5 bit brightness field of a pixel frame, from 0 to 31
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct U5(u8);
+
+impl U5 {
+    pub const MAX: U5 = U5(31);
+
+    /// `None` if `value` is above 31
+    pub const fn new(value: u8) -> Option<U5> {
+        match value {
+            0..=31 => Some(U5(value)),
+            _ => None,
+        }
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl Apa102Pixel {
+    /// Create a pixel, usable in `const` and `static` frames
+    pub const fn new(r: u8, g: u8, b: u8, brightness: u8) -> Self {
+        Self {
+            color: RGB8 { r, g, b },
+            brightness,
+            five_bit: None,
+        }
+    }
+
+    /// The 4 byte wire frame of this pixel alone, e.g. for a DMA pipeline
+    ///
+    /// Only the header and the channel order are applied. The header holds
+    /// `five_bit` if set, the brightness converted to 5 bits otherwise, and
+    /// the colors are sent as they are. `Apa102Encoder::encode_pixel` applies
+    /// the settings of a controller as well, like its global brightness.
+    pub fn encode(&self, pixel_order: PixelOrder) -> [u8; 4] {
+        let header = match self.five_bit {
+            Some(five_bit) => five_bit.get(),
+            None => five_bit(self.brightness),
+        };
+        let [a, b, c] = pixel_order.order(self.color.r, self.color.g, self.color.b);
+        [0xE0 | header, a, b, c]
+    }
+
+    /// Read a pixel back from its 4 byte wire frame, the inverse of `encode`
+    ///
+    /// The 5 bit field is kept in `five_bit`, so encoding the pixel again
+    /// gives the same bytes.
+    pub fn from_frame_bytes(bytes: [u8; 4], pixel_order: PixelOrder) -> Self {
+        let [r, g, b] = pixel_order.unorder([bytes[1], bytes[2], bytes[3]]);
+        Self {
+            five_bit: Some(U5(bytes[0] & 0x1F)),
+            ..Self::new(r, g, b, 255)
+        }
+    }
+
+    /// Mix this pixel with `other`, `amount` going from 0 for `self` to 255
+    /// for `other`
+    ///
+    /// The brightness of both is taken into account, see `blend`. Combined
+    /// with a curve from `easing` this makes for smooth transitions:
+    /// `from.lerp(to, ease8_in_out_quad(progress))`.
+    pub fn lerp(self, other: Self, amount: u8) -> Self {
+        blend(self, other, amount)
+    }
+
+    /// Dim this pixel by `scale / 256`, with a `scale` of 255 keeping it
+    /// unchanged
+    ///
+    /// The brightness is reduced first, keeping the full resolution of the
+    /// color. Only once it would drop below the lowest value the 5 bit
+    /// brightness field can still tell apart, the color is scaled for the
+    /// rest.
+    pub fn scaled(self, scale: u8) -> Self {
+        // The brightness the 5 bit field of 1 stands for, rounded up
+        const MIN_BRIGHTNESS: u8 = 255_u8.div_ceil(31);
+
+        let brightness = scale8(self.brightness, scale);
+        if brightness >= MIN_BRIGHTNESS {
+            return Self { brightness, ..self };
+        }
+        let brightness = self.brightness.min(MIN_BRIGHTNESS);
+        if brightness == 0 {
+            return self;
+        }
+        // Whatever the brightness can't take, at most 256
+        let color_scale = self.brightness as u32 * (scale as u32 + 1) / brightness as u32;
+        let channel = |c: u8| ((c as u32 * color_scale) >> 8) as u8;
+        Self::new(
+            channel(self.color.r),
+            channel(self.color.g),
+            channel(self.color.b),
+            brightness,
+        )
+    }
+
+    /// The light this pixel asks for, as a linear 16 bit color
+    ///
+    /// Each channel is the color multiplied by the brightness, so pixels
+    /// emitting the same light compare equal no matter how it is split
+    /// between the two. Full brightness white is 65535. The settings of the
+    /// controller, like its global brightness or gamma, aren't applied.
+    pub fn to_linear_rgb16(self) -> RGB16 {
+        let channel = |c: u8| {
+            let product = c as u32 * self.brightness as u32;
+            ((product * 65535 + 65025 / 2) / 65025) as u16
+        };
+        RGB16::new(
+            channel(self.color.r),
+            channel(self.color.g),
+            channel(self.color.b),
+        )
+    }
+
+    /// Convert a 16 bit color like `From<RGB16>`, mapping it through
+    /// the gamma curve `value^exponent` first
+    ///
+    /// The curve is computed in 16 bits, so dim colors keep more resolution
+    /// than with a `GammaTable`.
+    pub fn from_rgb16_with_gamma(color: RGB16, exponent: f32) -> Self {
+        Self::from_rgb16_with_gamma_and_brightness(color, exponent, 255)
+    }
+
+    /// Like `from_rgb16_with_gamma`, dimming the color by `brightness` after
+    /// the gamma curve
+    pub fn from_rgb16_with_gamma_and_brightness(
+        color: RGB16,
+        exponent: f32,
+        brightness: u8,
+    ) -> Self {
+        let channel = |c: u16| {
+            let value = powf(c as f32 / 65535.0, exponent) * brightness as f32 / 255.0;
+            (value * 65535.0 + 0.5) as u16
+        };
+        RGB16::new(channel(color.r), channel(color.g), channel(color.b)).into()
+    }
+}
+
+impl From<RGB8> for Apa102Pixel {
+    /// Convert a color at full brightness
+    ///
+    /// To write plain colors dimmer, lower the brightness of the controller
+    /// with `Apa102Encoder::set_brightness`.
+    fn from(color: RGB8) -> Self {
+        Self::new(color.r, color.g, color.b, 255)
+    }
+}
+
+impl From<(RGB8, u8)> for Apa102Pixel {
+    /// The brightness is applied with the `BrightnessStrategy` of the
+    /// controller, e.g. spread over the 5 bit field and the colors by
+    /// `Pseudo13`
+    fn from((color, brightness): (RGB8, u8)) -> Self {
+        Self::new(color.r, color.g, color.b, brightness)
+    }
+}
+
+impl From<(RGB8, U5)> for Apa102Pixel {
+    /// The 5 bit value is sent as is, see `five_bit`
+    fn from((color, five_bit): (RGB8, U5)) -> Self {
+        Self {
+            five_bit: Some(five_bit),
+            ..Self::new(color.r, color.g, color.b, 255)
+        }
+    }
+}
+
+impl From<u32> for Apa102Pixel {
+    /// Convert a packed `0xRRGGBB` color at full brightness, ignoring the
+    /// highest byte
+    fn from(color: u32) -> Self {
+        let [_, r, g, b] = color.to_be_bytes();
+        Self::new(r, g, b, 255)
+    }
+}
+
+impl From<RGB16> for Apa102Pixel {
+    /// Spread a 16 bit color over the color channels and the brightness
+    ///
+    /// The brightness is chosen as low as possible, so that with
+    /// `BrightnessStrategy::Pseudo13` more than 8 bits of the input survive.
+    fn from(color: RGB16) -> Self {
+        let max = color.r.max(color.g).max(color.b) as u32;
+        let brightness = max.div_ceil(255).clamp(1, 255);
+        let channel = |c: u16| ((c as u32 + brightness / 2) / brightness).min(255) as u8;
+        Self::new(
+            channel(color.r),
+            channel(color.g),
+            channel(color.b),
+            brightness as u8,
+        )
+    }
+}
+
+/// Set channel `index` of the pixels in a buffer of RGB triplets, ignoring
+/// channels beyond its end
+pub(crate) fn set_channel(pixels: &mut [RGB8], index: usize, value: u8) {
+    if let Some(pixel) = pixels.get_mut(index / 3) {
+        match index % 3 {
+            0 => pixel.r = value,
+            1 => pixel.g = value,
+            _ => pixel.b = value,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use embedded_hal::delay::DelayNs;
+
+use smart_leds_trait::SmartLedsWrite;
+
+use crate::Apa102Pixel;
+
+/// Plays back a precompiled animation, e.g. embedded with `include_bytes!`
+///
+/// The animation starts with an 8 byte header:
+///
+/// | Offset | Size | Content                                          |
+/// |--------|------|--------------------------------------------------|
+/// | 0      | 4    | `b"LEDS"`                                        |
+/// | 4      | 2    | Number of leds per frame, little endian          |
+/// | 6      | 1    | Frames per second                                |
+/// | 7      | 1    | Bytes per pixel, 3 (RGB) or 4 (RGB, brightness)  |
+///
+/// followed by the frames, every frame holding the pixels of all leds one
+/// after the other.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, ClockedLedBus, Player};
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # struct Delay;
+/// # impl embedded_hal::delay::DelayNs for Delay {
+/// #     fn delay_ns(&mut self, _: u32) {}
+/// # }
+/// # macro_rules! include_bytes {
+/// #     ($path:literal) => {
+/// #         &[]
+/// #     };
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let mut delay = Delay;
+/// static ANIMATION: &[u8] = include_bytes!("animation.leds");
+/// let player = Player::new(ANIMATION).unwrap();
+/// loop {
+///     player.play(&mut apa102, &mut delay)?;
+/// }
+/// # Ok::<(), ()>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Player<'a> {
+    frames: &'a [u8],
+    num_leds: usize,
+    fps: u8,
+    bytes_per_pixel: usize,
+}
+
+impl<'a> Player<'a> {
+    /// Parse the header of `data`
+    ///
+    /// Returns `None` if the header is invalid or the frames don't add up to
+    /// a whole number of frames.
+    pub fn new(data: &'a [u8]) -> Option<Player<'a>> {
+        let (header, frames) = data.split_at_checked(8)?;
+        if &header[..4] != b"LEDS" {
+            return None;
+        }
+        let num_leds = u16::from_le_bytes([header[4], header[5]]) as usize;
+        let fps = header[6];
+        let bytes_per_pixel = header[7] as usize;
+        if !matches!(bytes_per_pixel, 3 | 4) || fps == 0 {
+            return None;
+        }
+        let frame_len = num_leds * bytes_per_pixel;
+        if frame_len == 0 || frames.len() % frame_len != 0 {
+            return None;
+        }
+        Some(Self {
+            frames,
+            num_leds,
+            fps,
+            bytes_per_pixel,
+        })
+    }
+
+    /// Number of leds in every frame
+    pub fn num_leds(&self) -> usize {
+        self.num_leds
+    }
+
+    /// Number of frames shown per second
+    pub fn fps(&self) -> u8 {
+        self.fps
+    }
+
+    /// Number of frames of the animation
+    pub fn num_frames(&self) -> usize {
+        self.frames.len() / (self.num_leds * self.bytes_per_pixel)
+    }
+
+    /// Iterate over the pixels of the frame at `index`, `None` if there is no
+    /// such frame
+    pub fn frame(&self, index: usize) -> Option<impl Iterator<Item = Apa102Pixel> + 'a> {
+        let frame_len = self.num_leds * self.bytes_per_pixel;
+        let frame = self
+            .frames
+            .get(index * frame_len..(index + 1) * frame_len)?;
+        Some(frame.chunks_exact(self.bytes_per_pixel).map(|pixel| {
+            let brightness = pixel.get(3).copied().unwrap_or(255);
+            Apa102Pixel::new(pixel[0], pixel[1], pixel[2], brightness)
+        }))
+    }
+
+    /// Write every frame once, waiting for the next frame using `delay`
+    pub fn play<W, D>(&self, writer: &mut W, delay: &mut D) -> Result<(), W::Error>
+    where
+        W: SmartLedsWrite,
+        Apa102Pixel: Into<W::Color>,
+        D: DelayNs,
+    {
+        let frame_time_us = 1_000_000 / self.fps as u32;
+        for frame in (0..self.num_frames()).filter_map(|index| self.frame(index)) {
+            writer.write(frame)?;
+            delay.delay_us(frame_time_us);
+        }
+        Ok(())
+    }
+}
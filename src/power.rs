@@ -0,0 +1,273 @@
+use crate::conversion::five_bit;
+use crate::{Apa102Pixel, ChipVariant};
+
+/// Current drawn by a kind of leds, to estimate the current of a frame
+///
+/// The presets for each `ChipVariant` are rough datasheet values. For a
+/// tight power budget, measure a fully lit channel of the strip used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerModel {
+    /// Current of a fully lit red channel in mA
+    pub red_ma: u16,
+    /// Current of a fully lit green channel in mA
+    pub green_ma: u16,
+    /// Current of a fully lit blue channel in mA
+    pub blue_ma: u16,
+    /// Current of every led, even when turned off, in mA
+    pub quiescent_ma: u16,
+}
+
+impl PowerModel {
+    pub const fn new(red_ma: u16, green_ma: u16, blue_ma: u16, quiescent_ma: u16) -> Self {
+        Self {
+            red_ma,
+            green_ma,
+            blue_ma,
+            quiescent_ma,
+        }
+    }
+}
+
+impl From<ChipVariant> for PowerModel {
+    fn from(chip_variant: ChipVariant) -> Self {
+        match chip_variant {
+            ChipVariant::Apa102 => Self::new(20, 20, 20, 1),
+            ChipVariant::Sk9822 | ChipVariant::Hd107s => Self::new(18, 18, 18, 1),
+        }
+    }
+}
+
+/// Dims frames drawing more current than a budget, like
+/// `setMaxPowerInVoltsAndMilliamps` in FastLED
+///
+/// Frames are dimmed as a whole, keeping their colors, before being written:
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, Apa102Pixel, ChipVariant, ClockedLedBus, PowerLimiter};
+/// # use smart_leds_trait::SmartLedsWrite;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let mut frame = [Apa102Pixel::default(); 100];
+/// let mut limiter = PowerLimiter::new(2000, ChipVariant::Apa102);
+/// limiter.limit(&mut frame);
+/// apa102.write(frame.iter().copied())?;
+/// # Ok::<(), ()>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct PowerLimiter {
+    max_current_ma: u32,
+    model: PowerModel,
+    frames: u32,
+    limited_frames: u32,
+    last_scale: u8,
+}
+
+impl PowerLimiter {
+    /// new constructs a limiter keeping frames within `max_current_ma`, see
+    /// `estimate_current_ma` for `model`
+    pub fn new(max_current_ma: u32, model: impl Into<PowerModel>) -> Self {
+        Self {
+            max_current_ma,
+            model: model.into(),
+            frames: 0,
+            limited_frames: 0,
+            last_scale: 255,
+        }
+    }
+
+    /// Set the current frames are kept within, in mA
+    pub fn set_max_current_ma(&mut self, max_current_ma: u32) {
+        self.max_current_ma = max_current_ma;
+    }
+
+    /// Dim `frame` in place if it draws more than the budget
+    ///
+    /// Returns the scale applied, see `Apa102Pixel::scaled`, 255 meaning the
+    /// frame is unchanged. The current the leds draw when turned off can't be
+    /// reduced, so a budget below it turns all of them off.
+    pub fn limit(&mut self, frame: &mut [Apa102Pixel]) -> u8 {
+        let scale = self.dim(frame);
+        self.frames = self.frames.saturating_add(1);
+        if scale < 255 {
+            self.limited_frames = self.limited_frames.saturating_add(1);
+        }
+        self.last_scale = scale;
+        scale
+    }
+
+    /// Number of frames passed to `limit` since the last `reset_stats`
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Number of frames dimmed by `limit` since the last `reset_stats`,
+    /// e.g. to warn that the brightness is being reduced
+    pub fn limited_frames(&self) -> u32 {
+        self.limited_frames
+    }
+
+    /// The scale applied to the last frame, 255 if it wasn't dimmed
+    pub fn last_scale(&self) -> u8 {
+        self.last_scale
+    }
+
+    /// Reset the frame counters
+    pub fn reset_stats(&mut self) {
+        self.frames = 0;
+        self.limited_frames = 0;
+    }
+
+    fn dim(&self, frame: &mut [Apa102Pixel]) -> u8 {
+        let quiescent = frame.len() as u32 * self.model.quiescent_ma as u32;
+        let total = estimate_current_ma(frame, self.model);
+        if total <= self.max_current_ma {
+            return 255;
+        }
+        let lit = (total - quiescent) as u64;
+        if lit == 0 {
+            // Only the current of the leds turned off is over the budget
+            return turn_off(frame);
+        }
+        let budget = self.max_current_ma.saturating_sub(quiescent) as u64;
+        let mut scale = (budget * 256 / lit).saturating_sub(1) as u8;
+        // The 5 bit brightness field rounds up, so the first guess can still
+        // be slightly above the budget
+        let scaled = |scale| frame.iter().map(move |pixel| pixel.scaled(scale));
+        while scale > 0 && current_ma(scaled(scale), self.model) > self.max_current_ma {
+            scale -= 1;
+        }
+        if current_ma(scaled(scale), self.model) > self.max_current_ma {
+            return turn_off(frame);
+        }
+        for pixel in frame.iter_mut() {
+            *pixel = pixel.scaled(scale);
+        }
+        scale
+    }
+}
+
+/// Turn every pixel of `frame` off, returning the scale of 0 this amounts to
+fn turn_off(frame: &mut [Apa102Pixel]) -> u8 {
+    for pixel in frame.iter_mut() {
+        pixel.brightness = 0;
+    }
+    0
+}
+
+/// A curve lowering the brightness as the supply voltage drops, e.g. to
+/// keep a battery from browning out at full white
+///
+/// Above `full_mv` the brightness is left alone. Below it, it is lowered
+/// linearly down to `min_scale / 256` at `empty_mv` and below.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, Apa102Pixel, ClockedLedBus, Derating};
+/// # use smart_leds_trait::SmartLedsWrite;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # fn read_battery_mv() -> u16 {
+/// #     3600
+/// # }
+/// # let mut apa102 = Apa102::new(Spi);
+/// # let frame = [Apa102Pixel::default(); 100];
+/// apa102.encoder_mut().set_derating(Some(Derating::new(3700, 3300, 64)));
+/// loop {
+///     apa102.encoder_mut().set_supply_voltage_mv(read_battery_mv());
+///     apa102.write(frame.iter().copied())?;
+/// }
+/// # Ok::<(), ()>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Derating {
+    /// Voltage down to which the brightness is left alone, in mV
+    pub full_mv: u16,
+    /// Voltage at which the brightness is lowered the most, in mV
+    pub empty_mv: u16,
+    /// Scale applied at `empty_mv` and below
+    pub min_scale: u8,
+}
+
+impl Derating {
+    pub const fn new(full_mv: u16, empty_mv: u16, min_scale: u8) -> Self {
+        Self {
+            full_mv,
+            empty_mv,
+            min_scale,
+        }
+    }
+
+    /// Scale applied to the brightness at `supply_voltage_mv`, see `scale8`
+    pub fn scale(&self, supply_voltage_mv: u16) -> u8 {
+        if supply_voltage_mv >= self.full_mv {
+            return 255;
+        }
+        if supply_voltage_mv <= self.empty_mv {
+            return self.min_scale;
+        }
+        let range = (self.full_mv - self.empty_mv) as u32;
+        let above_empty = (supply_voltage_mv - self.empty_mv) as u32;
+        let span = 255 - self.min_scale as u32;
+        (self.min_scale as u32 + span * above_empty / range) as u8
+    }
+}
+
+/// Estimate the current in mA drawn by the leds while showing `frame`
+///
+/// `model` is a `PowerModel`, or a `ChipVariant` to use its preset. The
+/// brightness of each pixel is quantized to the 5 bit brightness field, as
+/// with `BrightnessStrategy::HardwareFiveBit`. The settings of the
+/// controller, like its global brightness, aren't applied.
+pub fn estimate_current_ma(frame: &[Apa102Pixel], model: impl Into<PowerModel>) -> u32 {
+    current_ma(frame.iter().copied(), model.into())
+}
+
+fn current_ma(pixels: impl Iterator<Item = Apa102Pixel>, model: PowerModel) -> u32 {
+    // Sum in units of 1 / (255 * 31) mA, the smallest step of a channel
+    let mut lit = 0;
+    let mut quiescent = 0;
+    for pixel in pixels {
+        let color = pixel.color;
+        let channels = color.r as u64 * model.red_ma as u64
+            + color.g as u64 * model.green_ma as u64
+            + color.b as u64 * model.blue_ma as u64;
+        lit += channels * five_bit(pixel.brightness) as u64;
+        quiescent += model.quiescent_ma as u64;
+    }
+    (lit.div_ceil(255 * 31) + quiescent).min(u32::MAX as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_frame_within_budget() {
+        let mut limiter = PowerLimiter::new(1000, ChipVariant::Apa102);
+        let mut frame = [Apa102Pixel::new(255, 255, 255, 255); 100];
+        let scale = limiter.limit(&mut frame);
+        assert!(0 < scale && scale < 255);
+        assert!(estimate_current_ma(&frame, ChipVariant::Apa102) <= 1000);
+        assert_eq!((limiter.frames(), limiter.limited_frames()), (1, 1));
+    }
+
+    #[test]
+    fn turns_off_frame_below_quiescent_current() {
+        let mut limiter = PowerLimiter::new(50, ChipVariant::Apa102);
+        let mut frame = [Apa102Pixel::default(); 100];
+        assert_eq!(limiter.limit(&mut frame), 0);
+        let mut frame = [Apa102Pixel::new(255, 255, 255, 255); 100];
+        assert_eq!(limiter.limit(&mut frame), 0);
+        assert!(frame.iter().all(|pixel| pixel.brightness == 0));
+    }
+}
@@ -0,0 +1,106 @@
+use core::ops::Range;
+
+use crate::{Apa102Pixel, BrightnessStrategy, PixelOrder};
+
+/// A named range of leds of a strip, e.g. a status indicator in front of a
+/// ring
+///
+/// A segment can have its own pixel order and brightness strategy, e.g. for
+/// a strip chained to one with a different kind of leds. These are applied
+/// when the segments are passed to `Apa102Encoder::set_segments`.
+///
+/// ```no_run
+/// # use apa102_spi::{Apa102, Apa102Buffered, ClockedLedBus, Segment};
+/// # use smart_leds_trait::RGB8;
+/// # struct Spi;
+/// # impl ClockedLedBus for Spi {
+/// #     type Error = ();
+/// #     fn write_bytes(&mut self, _: &[u8]) -> Result<(), ()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # const RED: RGB8 = RGB8::new(255, 0, 0);
+/// # let mut leds: Apa102Buffered<_, 28> = Apa102Buffered::new(Apa102::new(Spi));
+/// const SEGMENTS: [Segment; 2] = [Segment::new("status", 0, 4), Segment::new("ring", 4, 24)];
+/// leds.encoder_mut().set_segments(&SEGMENTS);
+/// leds.segment_mut("ring").unwrap().fill(RED.into());
+/// leds.flush()?;
+/// # Ok::<(), ()>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    name: &'static str,
+    start: usize,
+    len: usize,
+    pixel_order: Option<PixelOrder>,
+    brightness_strategy: Option<BrightnessStrategy>,
+}
+
+impl Segment {
+    pub const fn new(name: &'static str, start: usize, len: usize) -> Self {
+        Self {
+            name,
+            start,
+            len,
+            pixel_order: None,
+            brightness_strategy: None,
+        }
+    }
+
+    /// Use `pixel_order` for the leds of this segment
+    pub const fn with_pixel_order(mut self, pixel_order: PixelOrder) -> Self {
+        self.pixel_order = Some(pixel_order);
+        self
+    }
+
+    /// Use `brightness_strategy` for the leds of this segment
+    pub const fn with_brightness_strategy(
+        mut self,
+        brightness_strategy: BrightnessStrategy,
+    ) -> Self {
+        self.brightness_strategy = Some(brightness_strategy);
+        self
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub const fn pixel_order(&self) -> Option<PixelOrder> {
+        self.pixel_order
+    }
+
+    pub const fn brightness_strategy(&self) -> Option<BrightnessStrategy> {
+        self.brightness_strategy
+    }
+
+    /// Indices of the leds in the segment
+    pub const fn range(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+}
+
+/// Split `frame` into one slice per segment, in the order of `segments`, so
+/// each can be rendered on its own, e.g. by a different task
+///
+/// Returns `None` if segments overlap or reach past the end of `frame`.
+pub fn split_segments<'a, const S: usize>(
+    frame: &'a mut [Apa102Pixel],
+    segments: &[Segment; S],
+) -> Option<[&'a mut [Apa102Pixel]; S]> {
+    let mut order: [usize; S] = core::array::from_fn(|i| i);
+    order.sort_unstable_by_key(|&i| segments[i].start);
+    let mut parts: [Option<&'a mut [Apa102Pixel]>; S] = core::array::from_fn(|_| None);
+    let mut rest = frame;
+    let mut offset = 0;
+    for i in order {
+        let segment = segments[i];
+        let skip = segment.start.checked_sub(offset)?;
+        let (_, tail) = core::mem::take(&mut rest).split_at_mut_checked(skip)?;
+        let (part, tail) = tail.split_at_mut_checked(segment.len)?;
+        parts[i] = Some(part);
+        rest = tail;
+        offset = segment.start + segment.len;
+    }
+    Some(parts.map(|part| part.unwrap()))
+}
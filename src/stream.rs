@@ -0,0 +1,143 @@
+use embedded_io::ReadExactError;
+
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::Apa102Pixel;
+
+/// Marks the start of every frame
+const SYNC: [u8; 2] = [0xA5, 0x5A];
+
+/// Reads frames from a byte stream, like a UART, SD card or flash
+///
+/// Every frame starts with the sync bytes `0xA5 0x5A`, followed by the
+/// number of pixel bytes as little endian `u16` and the pixels as RGB
+/// triplets. Anything before the sync bytes is skipped, so a reader started
+/// in the middle of a stream picks up at the next frame. Frames that don't
+/// fit into the buffer of `N` bytes are dropped the same way.
+///
+/// Works with `embedded_io::Read` using the `embedded-io` feature, and with
+/// `embedded_io_async::Read` using the `embedded-io-async` feature.
+pub struct FrameReader<R, const N: usize> {
+    reader: R,
+    buffer: [u8; N],
+}
+
+/// Error forwarding a frame from a `FrameReader` to the leds
+#[derive(Debug)]
+pub enum StreamError<R, W> {
+    /// Reading the stream failed or it ended
+    Read(ReadExactError<R>),
+    /// Writing the frame failed
+    Write(W),
+}
+
+impl<R, const N: usize> FrameReader<R, N> {
+    pub fn new(reader: R) -> FrameReader<R, N> {
+        Self {
+            reader,
+            buffer: [0; N],
+        }
+    }
+
+    /// Free the owned reader consuming self
+    pub fn free(self) -> R {
+        self.reader
+    }
+
+    /// Whether a frame of `len` pixel bytes can be read into the buffer
+    fn fits(len: usize) -> bool {
+        len <= N && len.is_multiple_of(3)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R, const N: usize> FrameReader<R, N>
+where
+    R: embedded_io::Read,
+{
+    /// Read the next complete frame, blocking until it has been received
+    pub fn read_frame(
+        &mut self,
+    ) -> Result<impl Iterator<Item = Apa102Pixel> + '_, ReadExactError<R::Error>> {
+        loop {
+            let mut previous = 0;
+            loop {
+                let mut byte = [0];
+                self.reader.read_exact(&mut byte)?;
+                if [previous, byte[0]] == SYNC {
+                    break;
+                }
+                previous = byte[0];
+            }
+            let mut len = [0; 2];
+            self.reader.read_exact(&mut len)?;
+            let len = u16::from_le_bytes(len) as usize;
+            if Self::fits(len) {
+                self.reader.read_exact(&mut self.buffer[..len])?;
+                return Ok(pixels(&self.buffer[..len]));
+            }
+        }
+    }
+
+    /// Read the next complete frame and write it to `writer`
+    pub fn forward<W>(&mut self, writer: &mut W) -> Result<(), StreamError<R::Error, W::Error>>
+    where
+        W: SmartLedsWrite,
+        Apa102Pixel: Into<W::Color>,
+    {
+        let frame = self.read_frame().map_err(StreamError::Read)?;
+        writer.write(frame).map_err(StreamError::Write)
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<R, const N: usize> FrameReader<R, N>
+where
+    R: embedded_io_async::Read,
+{
+    /// Read the next complete frame, see `read_frame`
+    pub async fn read_frame_async(
+        &mut self,
+    ) -> Result<impl Iterator<Item = Apa102Pixel> + '_, ReadExactError<R::Error>> {
+        loop {
+            let mut previous = 0;
+            loop {
+                let mut byte = [0];
+                self.reader.read_exact(&mut byte).await?;
+                if [previous, byte[0]] == SYNC {
+                    break;
+                }
+                previous = byte[0];
+            }
+            let mut len = [0; 2];
+            self.reader.read_exact(&mut len).await?;
+            let len = u16::from_le_bytes(len) as usize;
+            if Self::fits(len) {
+                self.reader.read_exact(&mut self.buffer[..len]).await?;
+                return Ok(pixels(&self.buffer[..len]));
+            }
+        }
+    }
+
+    /// Read the next complete frame and write it to `writer`
+    ///
+    /// Only reading is asynchronous, the frame is written using the blocking
+    /// `SmartLedsWrite`.
+    pub async fn forward_async<W>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<(), StreamError<R::Error, W::Error>>
+    where
+        W: SmartLedsWrite,
+        Apa102Pixel: Into<W::Color>,
+    {
+        let frame = self.read_frame_async().await.map_err(StreamError::Read)?;
+        writer.write(frame).map_err(StreamError::Write)
+    }
+}
+
+fn pixels(bytes: &[u8]) -> impl Iterator<Item = Apa102Pixel> + '_ {
+    bytes
+        .chunks_exact(3)
+        .map(|rgb| RGB8::new(rgb[0], rgb[1], rgb[2]).into())
+}
@@ -0,0 +1,37 @@
+//! Helpers shared by the unit tests
+
+extern crate std;
+
+use std::vec::Vec;
+
+use core::convert::Infallible;
+
+use smart_leds_trait::RGB8;
+
+use crate::{Apa102Pixel, ClockedLedBus};
+
+/// A bus recording everything clocked out
+#[derive(Default)]
+pub(crate) struct RecordingBus {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl ClockedLedBus for RecordingBus {
+    type Error = Infallible;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+        self.bytes.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Feed `bytes` one at a time to `push`, e.g. wrapping the `push` of a
+/// decoder, collecting everything it returns
+pub(crate) fn feed<T>(bytes: &[u8], push: impl FnMut(u8) -> Option<T>) -> Vec<T> {
+    bytes.iter().copied().filter_map(push).collect()
+}
+
+/// The colors of decoded pixels
+pub(crate) fn colors(pixels: impl Iterator<Item = Apa102Pixel>) -> Vec<RGB8> {
+    pixels.map(|pixel| pixel.color).collect()
+}
@@ -0,0 +1,214 @@
+use smart_leds_trait::RGB8;
+
+use crate::pixel::set_channel;
+use crate::Apa102Pixel;
+
+const SERIAL_START: u8 = 0xC9;
+const NET_START: u8 = 0x9C;
+const DATA_FRAME: u8 = 0xDA;
+const END: u8 = 0x36;
+
+/// Decodes TPM2 frames received over a serial port
+///
+/// Every packet starts with `0xC9`, a type and the data size as big endian
+/// `u16`, and ends with `0x36`. Data frames hold the pixels as RGB triplets
+/// and are decoded into a buffer of `N` pixels, leds beyond that are
+/// dropped. Other packets, like commands, are skipped.
+pub struct Tpm2Decoder<const N: usize> {
+    state: State,
+    pixels: [RGB8; N],
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Start,
+    Type,
+    /// Type of the packet and the upper byte of its size, once received
+    Size(u8, Option<u8>),
+    /// Type, size and number of data bytes received
+    Data(u8, usize, usize),
+    /// Type and size of a packet waiting for its end byte
+    End(u8, usize),
+}
+
+impl<const N: usize> Tpm2Decoder<N> {
+    pub fn new() -> Tpm2Decoder<N> {
+        Self {
+            state: State::Start,
+            pixels: [RGB8::default(); N],
+        }
+    }
+
+    /// Feed the next received byte, returning the pixels once a data frame
+    /// is complete
+    ///
+    /// Frames not ending in the end byte are dropped.
+    pub fn push(&mut self, byte: u8) -> Option<impl Iterator<Item = Apa102Pixel> + '_> {
+        self.state = match self.state {
+            State::Start if byte == SERIAL_START => State::Type,
+            State::Start => State::Start,
+            State::Type => State::Size(byte, None),
+            State::Size(kind, None) => State::Size(kind, Some(byte)),
+            State::Size(kind, Some(high)) => match u16::from_be_bytes([high, byte]) as usize {
+                0 => State::End(kind, 0),
+                len => State::Data(kind, len, 0),
+            },
+            State::Data(kind, len, received) => {
+                if kind == DATA_FRAME {
+                    set_channel(&mut self.pixels, received, byte);
+                }
+                if received + 1 < len {
+                    State::Data(kind, len, received + 1)
+                } else {
+                    State::End(kind, len)
+                }
+            }
+            State::End(kind, len) => {
+                self.state = State::Start;
+                if byte == END && kind == DATA_FRAME {
+                    let num_leds = (len / 3).min(N);
+                    return Some(self.pixels[..num_leds].iter().map(|&c| c.into()));
+                }
+                State::Start
+            }
+        };
+        None
+    }
+}
+
+impl<const N: usize> Default for Tpm2Decoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A TPM2.net data packet, as received over UDP
+///
+/// Large frames are split over several packets, numbered from 1 to
+/// `num_packets`.
+#[derive(Clone, Copy, Debug)]
+pub struct Tpm2NetPacket<'a> {
+    pub packet_number: u8,
+    pub num_packets: u8,
+    data: &'a [u8],
+}
+
+impl<'a> Tpm2NetPacket<'a> {
+    /// Parse a whole UDP payload, `None` if it isn't a valid data packet
+    pub fn parse(packet: &'a [u8]) -> Option<Tpm2NetPacket<'a>> {
+        let (&[start, kind, high, low, packet_number, num_packets], rest) =
+            packet.split_first_chunk::<6>()?;
+        let len = u16::from_be_bytes([high, low]) as usize;
+        if start != NET_START || kind != DATA_FRAME || rest.get(len) != Some(&END) {
+            return None;
+        }
+        Some(Self {
+            packet_number,
+            num_packets,
+            data: &rest[..len],
+        })
+    }
+
+    /// Iterate over the pixels of this packet
+    pub fn pixels(&self) -> impl Iterator<Item = Apa102Pixel> + 'a {
+        self.data
+            .chunks_exact(3)
+            .map(|rgb| RGB8::new(rgb[0], rgb[1], rgb[2]).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::test_util::{colors, feed};
+
+    fn packet(start: u8, kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut packet = [start, kind].to_vec();
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(data);
+        packet.push(END);
+        packet
+    }
+
+    #[test]
+    fn decodes_data_frame() {
+        let mut decoder: Tpm2Decoder<4> = Tpm2Decoder::new();
+        let bytes = packet(SERIAL_START, DATA_FRAME, &[1, 2, 3, 4, 5, 6]);
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(1, 2, 3), RGB8::new(4, 5, 6)]]);
+    }
+
+    #[test]
+    fn drops_leds_beyond_buffer() {
+        let mut decoder: Tpm2Decoder<1> = Tpm2Decoder::new();
+        let bytes = packet(SERIAL_START, DATA_FRAME, &[1, 2, 3, 4, 5, 6]);
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(1, 2, 3)]]);
+    }
+
+    #[test]
+    fn drops_frame_without_end_byte() {
+        let mut decoder: Tpm2Decoder<4> = Tpm2Decoder::new();
+        // The size announces one byte less than sent
+        let mut bytes = packet(SERIAL_START, DATA_FRAME, &[1, 2, 3]);
+        bytes[3] = 2;
+        bytes.extend(packet(SERIAL_START, DATA_FRAME, &[4, 5, 6]));
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(4, 5, 6)]]);
+    }
+
+    #[test]
+    fn skips_other_packets() {
+        let mut decoder: Tpm2Decoder<4> = Tpm2Decoder::new();
+        let mut bytes = packet(SERIAL_START, 0xC0, &[1, 2, 3]);
+        bytes.extend(packet(SERIAL_START, DATA_FRAME, &[]));
+        bytes.extend(packet(SERIAL_START, DATA_FRAME, &[4, 5, 6]));
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[].to_vec(), [RGB8::new(4, 5, 6)].to_vec()]);
+    }
+
+    #[test]
+    fn resyncs_after_garbage() {
+        let mut decoder: Tpm2Decoder<4> = Tpm2Decoder::new();
+        let mut bytes = [0x00, 0x36, 0xDA].to_vec();
+        bytes.extend(packet(SERIAL_START, DATA_FRAME, &[1, 2, 3]));
+        let frames = feed(&bytes, |byte| decoder.push(byte).map(colors));
+        assert_eq!(frames, [[RGB8::new(1, 2, 3)]]);
+    }
+
+    #[test]
+    fn parses_net_packet() {
+        let mut bytes = packet(NET_START, DATA_FRAME, &[1, 2, 3, 4, 5, 6]);
+        bytes.splice(4..4, [2, 3]);
+        let packet = Tpm2NetPacket::parse(&bytes).unwrap();
+        assert_eq!((packet.packet_number, packet.num_packets), (2, 3));
+        assert_eq!(
+            colors(packet.pixels()),
+            [RGB8::new(1, 2, 3), RGB8::new(4, 5, 6)]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_net_packets() {
+        let mut valid = packet(NET_START, DATA_FRAME, &[1, 2, 3]);
+        valid.splice(4..4, [1, 1]);
+        assert!(Tpm2NetPacket::parse(&valid).is_some());
+
+        let mut wrong_start = valid.clone();
+        wrong_start[0] = SERIAL_START;
+        assert!(Tpm2NetPacket::parse(&wrong_start).is_none());
+        let mut wrong_type = valid.clone();
+        wrong_type[1] = 0xC0;
+        assert!(Tpm2NetPacket::parse(&wrong_type).is_none());
+        // Cut off before the end byte, and longer than announced
+        assert!(Tpm2NetPacket::parse(&valid[..valid.len() - 1]).is_none());
+        let mut too_long = valid.clone();
+        too_long.insert(valid.len() - 1, 4);
+        assert!(Tpm2NetPacket::parse(&too_long).is_none());
+        assert!(Tpm2NetPacket::parse(&valid[..5]).is_none());
+    }
+}
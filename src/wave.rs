@@ -0,0 +1,77 @@
+//! Integer waveforms, ported from FastLED
+//!
+//! Instead of reading a clock, the `beat` functions take the time in
+//! milliseconds from the caller, e.g. from a hardware timer, so animations
+//! behave the same on every board.
+
+use crate::math::scale8;
+
+/// Sine of `theta`, a full period going from 0 to 255, scaled to 0..=255
+///
+/// Uses a piecewise linear approximation, accurate to about 2%.
+pub fn sin8(theta: u8) -> u8 {
+    // Offset and slope of the four linear segments of a quarter period
+    const SEGMENTS: [(u8, u8); 4] = [(0, 49), (49, 41), (90, 27), (117, 10)];
+
+    let mut offset = theta;
+    if theta & 0x40 != 0 {
+        offset = 255 - offset;
+    }
+    offset &= 0x3F;
+    let mut segment_offset = offset & 0x0F;
+    if theta & 0x40 != 0 {
+        segment_offset += 1;
+    }
+    let (base, slope) = SEGMENTS[(offset >> 4) as usize];
+    let y = ((slope as u16 * segment_offset as u16) >> 4) as i16 + base as i16;
+    let y = if theta & 0x80 != 0 { -y } else { y };
+    (y + 128) as u8
+}
+
+/// Cosine of `theta`, see `sin8`
+pub fn cos8(theta: u8) -> u8 {
+    sin8(theta.wrapping_add(64))
+}
+
+/// Triangle wave going from 0 up to 254 and back down over one period
+pub fn triwave8(theta: u8) -> u8 {
+    let theta = if theta & 0x80 != 0 {
+        255 - theta
+    } else {
+        theta
+    };
+    theta << 1
+}
+
+/// Sawtooth wave repeating `bpm` times per minute at the time `t_ms`
+pub fn beat8(bpm: u8, t_ms: u32) -> u8 {
+    // The beats per minute in 8.8 fixed point, times 65536 / 60000
+    let beat = t_ms.wrapping_mul((bpm as u32) << 8).wrapping_mul(280) >> 16;
+    (beat >> 8) as u8
+}
+
+/// Sine wave between `lowest` and `highest`, repeating `bpm` times per
+/// minute at the time `t_ms`
+///
+/// `phase_offset` shifts the wave by a fraction of its period, e.g. to move
+/// several leds out of step with each other.
+pub fn beatsin8(bpm: u8, lowest: u8, highest: u8, t_ms: u32, phase_offset: u8) -> u8 {
+    let wave = sin8(beat8(bpm, t_ms).wrapping_add(phase_offset));
+    scale_to_range(wave, lowest, highest)
+}
+
+/// Triangle wave between `lowest` and `highest`, see `beatsin8`
+pub fn beattri8(bpm: u8, lowest: u8, highest: u8, t_ms: u32, phase_offset: u8) -> u8 {
+    let wave = triwave8(beat8(bpm, t_ms).wrapping_add(phase_offset));
+    scale_to_range(wave, lowest, highest)
+}
+
+/// Sawtooth wave between `lowest` and `highest`, see `beatsin8`
+pub fn beatsaw8(bpm: u8, lowest: u8, highest: u8, t_ms: u32, phase_offset: u8) -> u8 {
+    let wave = beat8(bpm, t_ms).wrapping_add(phase_offset);
+    scale_to_range(wave, lowest, highest)
+}
+
+fn scale_to_range(wave: u8, lowest: u8, highest: u8) -> u8 {
+    lowest.wrapping_add(scale8(wave, highest.wrapping_sub(lowest)))
+}
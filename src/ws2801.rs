@@ -0,0 +1,138 @@
+use embedded_hal::delay::DelayNs;
+
+use smart_leds_trait::{SmartLedsWrite, RGB8};
+
+use crate::chunked::Chunked;
+use crate::{ClockedLedBus, PixelOrder};
+
+/// How a `Ws2801` makes the leds latch the frame it wrote
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Latch {
+    /// Wait this many µs after every frame using the delay provider, 0
+    /// skipping the wait, e.g. when frames are written less often than every
+    /// 500µs anyway
+    Delay(u32),
+    /// Clock out this many zero bytes after the pixels instead of waiting,
+    /// for setups without a delay provider. WS2801 leds only latch once the
+    /// clock stops, so frames still need to be at least 500µs apart.
+    IdleBytes(usize),
+}
+
+/// The delay provider of a `Ws2801` latching with `Latch::IdleBytes`, see
+/// `Ws2801::new_with_idle_bytes`
+pub struct NoDelay(());
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _: u32) {}
+}
+
+/// A controller for a series of WS2801 LEDs
+///
+/// WS2801 leds take raw 24 bit colors and latch them once the clock has been
+/// idle for 500µs. By default the controller waits for that using `delay`
+/// after every frame, see `Latch`.
+pub struct Ws2801<SPI, D = NoDelay> {
+    spi: SPI,
+    delay: D,
+    pixel_order: PixelOrder,
+    latch: Latch,
+}
+
+impl<SPI, D> Ws2801<SPI, D>
+where
+    D: DelayNs,
+{
+    /// new constructs a controller for a series of WS2801 LEDs.
+    /// PixelOrder defaults to RGB and the latch to a 500µs delay, both can be
+    /// customized using new_with_options()
+    pub fn new(spi: SPI, delay: D) -> Ws2801<SPI, D> {
+        Self::new_with_options(spi, delay, PixelOrder::RGB, Latch::Delay(500))
+    }
+
+    /// The options are the order the colors are sent in and how the leds are
+    /// made to latch each frame
+    pub fn new_with_options(
+        spi: SPI,
+        delay: D,
+        pixel_order: PixelOrder,
+        latch: Latch,
+    ) -> Ws2801<SPI, D> {
+        Self {
+            spi,
+            delay,
+            pixel_order,
+            latch,
+        }
+    }
+}
+
+impl<SPI> Ws2801<SPI, NoDelay> {
+    /// new_with_idle_bytes constructs a controller without a delay provider,
+    /// latching with `Latch::IdleBytes(idle_bytes)`
+    pub fn new_with_idle_bytes(
+        spi: SPI,
+        pixel_order: PixelOrder,
+        idle_bytes: usize,
+    ) -> Ws2801<SPI, NoDelay> {
+        Self {
+            spi,
+            delay: NoDelay(()),
+            pixel_order,
+            latch: Latch::IdleBytes(idle_bytes),
+        }
+    }
+}
+
+impl<SPI, D> Ws2801<SPI, D> {
+    /// Borrow the SPI, e.g. to change its frequency, without consuming self
+    pub fn bus_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// Free the owned resources consuming self
+    pub fn free(self) -> (SPI, D) {
+        (self.spi, self.delay)
+    }
+}
+
+impl<SPI, D> SmartLedsWrite for Ws2801<SPI, D>
+where
+    SPI: ClockedLedBus,
+    D: DelayNs,
+{
+    type Color = RGB8;
+    type Error = SPI::Error;
+    /// Write all the items of an iterator to a WS2801 strip
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), SPI::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let mut chunked = Chunked::new(&mut self.spi);
+        for item in iterator {
+            let item = item.into();
+            chunked.push(&self.pixel_order.order(item.r, item.g, item.b))?;
+        }
+        if let Latch::IdleBytes(idle_bytes) = self.latch {
+            chunked.fill(0x00, idle_bytes)?;
+        }
+        chunked.finish()?;
+        if let Latch::Delay(latch_delay_us @ 1..) = self.latch {
+            self.delay.delay_us(latch_delay_us);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::RecordingBus;
+
+    #[test]
+    fn latches_with_idle_bytes() {
+        let mut ws2801 = Ws2801::new_with_idle_bytes(RecordingBus::default(), PixelOrder::RGB, 3);
+        ws2801.write([RGB8::new(1, 2, 3)]).unwrap();
+        assert_eq!(ws2801.free().0.bytes, [1, 2, 3, 0, 0, 0]);
+    }
+}